@@ -0,0 +1,24 @@
+-- Add migration script here
+CREATE TABLE queues
+(
+    guild_id            bigint PRIMARY KEY,
+    text_channel_id     bigint,
+    voice_channel_id    bigint,
+    loop_mode           smallint NOT NULL DEFAULT 0,
+    position            bigint NOT NULL DEFAULT 0,
+    users               bigint[] NOT NULL DEFAULT array[]::bigint[],
+    user_loop_modes     smallint[] NOT NULL DEFAULT array[]::smallint[]
+);
+
+CREATE TABLE queued_tracks
+(
+    guild_id            bigint NOT NULL REFERENCES queues ON DELETE CASCADE,
+    ordinal             integer NOT NULL,
+    query               text NOT NULL,
+    title               text NOT NULL,
+    artist              text NOT NULL,
+    length              bigint NOT NULL,
+    requester           bigint NOT NULL,
+    lava_track          text,
+    PRIMARY KEY (guild_id, ordinal)
+)
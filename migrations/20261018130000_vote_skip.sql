@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE guilds
+    ADD COLUMN vote_skip            boolean NOT NULL DEFAULT false,
+    ADD COLUMN vote_skip_threshold  smallint NOT NULL DEFAULT 50
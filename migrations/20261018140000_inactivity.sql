@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE guilds
+    ADD COLUMN alone_timeout        integer NOT NULL DEFAULT 300,
+    ADD COLUMN idle_timeout         integer NOT NULL DEFAULT 300
@@ -0,0 +1,6 @@
+-- Add migration script here
+ALTER TABLE guilds
+    ADD COLUMN max_queue_size       integer NOT NULL DEFAULT 0,
+    ADD COLUMN max_user_tracks      integer NOT NULL DEFAULT 0,
+    ADD COLUMN max_track_length     integer NOT NULL DEFAULT 0,
+    ADD COLUMN max_playlist_size    integer NOT NULL DEFAULT 0
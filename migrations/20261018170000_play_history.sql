@@ -0,0 +1,13 @@
+-- Add migration script here
+CREATE TABLE play_history
+(
+    id                  bigserial PRIMARY KEY,
+    guild_id            bigint NOT NULL,
+    uri                 text NOT NULL,
+    title               text NOT NULL,
+    requester           bigint NOT NULL,
+    started_at          timestamptz NOT NULL DEFAULT now(),
+    skipped             boolean NOT NULL DEFAULT false
+);
+
+CREATE INDEX play_history_guild_id_idx ON play_history (guild_id, id)
@@ -0,0 +1,21 @@
+-- Add migration script here
+CREATE TABLE saved_playlists
+(
+    id                  bigserial PRIMARY KEY,
+    owner               bigint NOT NULL,
+    name                text NOT NULL,
+    shared              boolean NOT NULL DEFAULT false,
+    UNIQUE (owner, name)
+);
+
+CREATE TABLE saved_playlist_tracks
+(
+    playlist_id         bigint NOT NULL REFERENCES saved_playlists (id) ON DELETE CASCADE,
+    ordinal             integer NOT NULL,
+    query               text NOT NULL,
+    title               text NOT NULL,
+    artist              text NOT NULL,
+    length              bigint NOT NULL,
+    lava_track          text,
+    PRIMARY KEY (playlist_id, ordinal)
+)
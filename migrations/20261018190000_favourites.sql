@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE favourites
+(
+    id                  bigserial PRIMARY KEY,
+    user_id             bigint NOT NULL,
+    query               text NOT NULL,
+    title               text NOT NULL,
+    artist              text NOT NULL,
+    length              bigint NOT NULL,
+    lava_track          text,
+    UNIQUE (user_id, query)
+)
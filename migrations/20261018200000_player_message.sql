@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE queues
+    ADD COLUMN player_channel_id bigint,
+    ADD COLUMN player_message_id bigint
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE guilds
+    ADD COLUMN queue_page_size      smallint NOT NULL DEFAULT 20
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE guilds
+    ADD COLUMN autoplay             boolean NOT NULL DEFAULT false
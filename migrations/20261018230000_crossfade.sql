@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE guilds
+    ADD COLUMN crossfade            smallint NOT NULL DEFAULT 0
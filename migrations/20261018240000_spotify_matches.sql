@@ -0,0 +1,6 @@
+-- Add migration script here
+CREATE TABLE spotify_matches
+(
+    spotify_id          text PRIMARY KEY,
+    lava_track          text NOT NULL
+)
@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE queued_tracks
+    ADD COLUMN spotify_id           text,
+    ADD COLUMN spotify_name         text,
+    ADD COLUMN isrc                 text
@@ -1,5 +1,6 @@
-use crate::{error::Error, Context};
-use serenity::model::prelude::ChannelType::Voice;
+use crate::{error::Error, music::queue::DuplicatePolicy, Context};
+use serenity::model::prelude::{ChannelType::Voice, GuildChannel, Role, RoleId};
+use std::time::Duration;
 
 #[poise::command(slash_command, owners_only, category = "Admin")]
 pub async fn roundrobin(
@@ -9,9 +10,9 @@ pub async fn roundrobin(
     let guild_id = ctx.guild_id().unwrap();
 
     let data = ctx.data();
-    let queue = data.guilds.get_queue(guild_id).await;
+    let queue = data.guilds.get_queue(guild_id).await?;
     let mut queue_lock = queue.lock().await;
-    queue_lock.set_round_robin(setting);
+    queue_lock.set_round_robin(setting).await;
 
     let database = &data.database;
 
@@ -84,3 +85,496 @@ pub async fn minecraftchannel(
 
     Ok(())
 }
+
+#[poise::command(slash_command, owners_only, category = "Admin")]
+pub async fn voteskip(
+    ctx: Context<'_>,
+    #[description = "On/Off"] setting: bool,
+    #[description = "Share of listeners needed to skip, in percent"]
+    #[min = 1]
+    #[max = 100]
+    threshold: Option<u8>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    let data = ctx.data();
+    let guild = data.guilds.get(guild_id).await?;
+    let mut guild_lock = guild.lock().await;
+    guild_lock.vote_skip = setting;
+    if let Some(threshold) = threshold {
+        guild_lock.vote_skip_threshold = threshold;
+    }
+
+    let database = &data.database;
+
+    sqlx::query!(
+        "INSERT INTO guilds (guild_id, vote_skip, vote_skip_threshold)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (guild_id) DO UPDATE
+            SET vote_skip = $2,
+                vote_skip_threshold = $3",
+        guild_id.0 as i64,
+        setting,
+        guild_lock.vote_skip_threshold as i16
+    )
+    .execute(database)
+    .await?;
+
+    if setting {
+        ctx.say(format!(
+            "Vote skip is now on, {}% of listeners need to vote to skip a track.",
+            guild_lock.vote_skip_threshold
+        ))
+        .await?;
+    } else {
+        ctx.say("Vote skip is now off.").await?;
+    }
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    owners_only,
+    category = "Admin",
+    subcommands("djrole_add", "djrole_remove", "djrole_list")
+)]
+pub async fn djrole(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only, rename = "add", category = "Admin")]
+pub async fn djrole_add(ctx: Context<'_>, #[description = "Role"] role: Role) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guilds.get(guild_id).await?;
+    let mut guild_lock = guild.lock().await;
+    if guild_lock.dj_role_ids.contains(&role.id) {
+        return Err(Error::Command(format!(
+            "{} is already a DJ role",
+            role.name
+        )));
+    }
+    guild_lock.dj_role_ids.push(role.id);
+    save_dj_roles(&ctx, &guild_lock.dj_role_ids).await?;
+
+    ctx.say(format!("{} is now a DJ role.", role.name)).await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only, rename = "remove", category = "Admin")]
+pub async fn djrole_remove(
+    ctx: Context<'_>,
+    #[description = "Role"] role: Role,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guilds.get(guild_id).await?;
+    let mut guild_lock = guild.lock().await;
+    if !guild_lock.dj_role_ids.contains(&role.id) {
+        return Err(Error::Command(format!("{} is not a DJ role", role.name)));
+    }
+    guild_lock.dj_role_ids.retain(|id| *id != role.id);
+    save_dj_roles(&ctx, &guild_lock.dj_role_ids).await?;
+
+    ctx.say(format!("{} is no longer a DJ role.", role.name))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only, rename = "list", category = "Admin")]
+pub async fn djrole_list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let guild = ctx.data().guilds.get(guild_id).await?;
+    let dj_role_ids = guild.lock().await.dj_role_ids.clone();
+
+    let roles = if dj_role_ids.is_empty() {
+        "No DJ roles set, everyone can control the music.".to_string()
+    } else {
+        dj_role_ids
+            .iter()
+            .map(|id| format!("<@&{}>", id.0))
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+    ctx.send(|m| m.embed(|e| e.title("DJ roles").description(roles)))
+        .await?;
+
+    Ok(())
+}
+
+async fn save_dj_roles(ctx: &Context<'_>, dj_role_ids: &[RoleId]) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let dj_role_ids: Vec<i64> = dj_role_ids.iter().map(|id| id.0 as i64).collect();
+    sqlx::query!(
+        "INSERT INTO guilds (guild_id, dj_role_ids)
+        VALUES ($1, $2)
+        ON CONFLICT (guild_id) DO UPDATE
+            SET dj_role_ids = $2",
+        guild_id.0 as i64,
+        &dj_role_ids
+    )
+    .execute(&ctx.data().database)
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(
+    slash_command,
+    owners_only,
+    category = "Admin",
+    subcommands("musicchannel_text", "musicchannel_voice")
+)]
+pub async fn musicchannel(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only, rename = "text", category = "Admin")]
+pub async fn musicchannel_text(
+    ctx: Context<'_>,
+    #[description = "Text channel, leave empty to allow all channels"]
+    #[channel_types("Text")]
+    channel: Option<GuildChannel>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let channel_id = channel.map(|channel| channel.id);
+
+    let data = ctx.data();
+    let guild = data.guilds.get(guild_id).await?;
+    let mut guild_lock = guild.lock().await;
+    guild_lock.text_channel_id = channel_id;
+    if let Some(channel_id) = channel_id {
+        let mut queue_lock = guild_lock.queue.lock().await;
+        queue_lock.channel_id = Some(channel_id);
+        queue_lock.save().await;
+    }
+
+    let database = &data.database;
+
+    sqlx::query!(
+        "INSERT INTO guilds (guild_id, text_channel_id)
+        VALUES ($1, $2)
+        ON CONFLICT (guild_id) DO UPDATE
+            SET text_channel_id = $2",
+        guild_id.0 as i64,
+        channel_id.map(|id| id.0 as i64)
+    )
+    .execute(database)
+    .await?;
+
+    match channel_id {
+        Some(channel_id) => {
+            ctx.say(format!("Music commands are now bound to <#{channel_id}>."))
+                .await?
+        }
+        None => {
+            ctx.say("Music commands can now be used in any channel.")
+                .await?
+        }
+    };
+
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only, rename = "voice", category = "Admin")]
+pub async fn musicchannel_voice(
+    ctx: Context<'_>,
+    #[description = "Voice channel, leave empty to allow all channels"]
+    #[channel_types("Voice")]
+    channel: Option<GuildChannel>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let channel_id = channel.map(|channel| channel.id);
+
+    let data = ctx.data();
+    let guild = data.guilds.get(guild_id).await?;
+    guild.lock().await.voice_channel_id = channel_id;
+
+    let database = &data.database;
+
+    sqlx::query!(
+        "INSERT INTO guilds (guild_id, voice_channel_id)
+        VALUES ($1, $2)
+        ON CONFLICT (guild_id) DO UPDATE
+            SET voice_channel_id = $2",
+        guild_id.0 as i64,
+        channel_id.map(|id| id.0 as i64)
+    )
+    .execute(database)
+    .await?;
+
+    match channel_id {
+        Some(channel_id) => {
+            ctx.say(format!("Music can now only be played in <#{channel_id}>."))
+                .await?
+        }
+        None => ctx.say("Music can now be played in any channel.").await?,
+    };
+
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only, category = "Admin")]
+pub async fn inactivity(
+    ctx: Context<'_>,
+    #[description = "Seconds to stay in an empty voice channel, 0 to stay forever"]
+    #[min = 0]
+    alone_timeout: Option<u32>,
+    #[description = "Seconds to stay after the queue has ended, 0 to stay forever"]
+    #[min = 0]
+    idle_timeout: Option<u32>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    let data = ctx.data();
+    let guild = data.guilds.get(guild_id).await?;
+    let mut guild_lock = guild.lock().await;
+    if let Some(alone_timeout) = alone_timeout {
+        guild_lock.alone_timeout = Duration::from_secs(alone_timeout.into());
+    }
+    if let Some(idle_timeout) = idle_timeout {
+        guild_lock.idle_timeout = Duration::from_secs(idle_timeout.into());
+    }
+
+    let database = &data.database;
+
+    sqlx::query!(
+        "INSERT INTO guilds (guild_id, alone_timeout, idle_timeout)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (guild_id) DO UPDATE
+            SET alone_timeout = $2,
+                idle_timeout = $3",
+        guild_id.0 as i64,
+        guild_lock.alone_timeout.as_secs() as i32,
+        guild_lock.idle_timeout.as_secs() as i32
+    )
+    .execute(database)
+    .await?;
+
+    ctx.say(format!(
+        "Leaving empty voice channels after {}s and idle voice channels after {}s (0 means never).",
+        guild_lock.alone_timeout.as_secs(),
+        guild_lock.idle_timeout.as_secs()
+    ))
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only, category = "Admin")]
+pub async fn limits(
+    ctx: Context<'_>,
+    #[description = "Maximum number of tracks in the queue, 0 for no limit"]
+    #[min = 0]
+    max_queue_size: Option<u32>,
+    #[description = "Maximum number of tracks per user, 0 for no limit"]
+    #[min = 0]
+    max_user_tracks: Option<u32>,
+    #[description = "Maximum track length in seconds, 0 for no limit"]
+    #[min = 0]
+    max_track_length: Option<u32>,
+    #[description = "Maximum number of tracks imported from a playlist, 0 for no limit"]
+    #[min = 0]
+    max_playlist_size: Option<u32>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    let data = ctx.data();
+    let guild = data.guilds.get(guild_id).await?;
+    let mut guild_lock = guild.lock().await;
+    let limits = &mut guild_lock.limits;
+    if let Some(max_queue_size) = max_queue_size {
+        limits.max_queue_size = max_queue_size as usize;
+    }
+    if let Some(max_user_tracks) = max_user_tracks {
+        limits.max_user_tracks = max_user_tracks as usize;
+    }
+    if let Some(max_track_length) = max_track_length {
+        limits.max_track_length = Duration::from_secs(max_track_length.into());
+    }
+    if let Some(max_playlist_size) = max_playlist_size {
+        limits.max_playlist_size = max_playlist_size as usize;
+    }
+
+    let database = &data.database;
+
+    sqlx::query!(
+        "INSERT INTO guilds (guild_id, max_queue_size, max_user_tracks, max_track_length, max_playlist_size)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (guild_id) DO UPDATE
+            SET max_queue_size = $2,
+                max_user_tracks = $3,
+                max_track_length = $4,
+                max_playlist_size = $5",
+        guild_id.0 as i64,
+        limits.max_queue_size as i32,
+        limits.max_user_tracks as i32,
+        limits.max_track_length.as_secs() as i32,
+        limits.max_playlist_size as i32
+    )
+    .execute(database)
+    .await?;
+
+    let limit = |value: usize| {
+        if value == 0 {
+            "no limit".to_string()
+        } else {
+            value.to_string()
+        }
+    };
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.title("Queue limits").description(format!(
+                "Queue size: {}\nTracks per user: {}\nTrack length: {}\nPlaylist size: {}",
+                limit(limits.max_queue_size),
+                limit(limits.max_user_tracks),
+                if limits.max_track_length.is_zero() {
+                    "no limit".to_string()
+                } else {
+                    crate::music::utils::length_to_string(limits.max_track_length.as_secs())
+                },
+                limit(limits.max_playlist_size)
+            ))
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only, category = "Admin")]
+pub async fn duplicates(
+    ctx: Context<'_>,
+    #[description = "Policy"] policy: DuplicatePolicy,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    let data = ctx.data();
+    let guild = data.guilds.get(guild_id).await?;
+    guild.lock().await.duplicate_policy = policy;
+
+    let database = &data.database;
+
+    sqlx::query!(
+        "INSERT INTO guilds (guild_id, duplicate_policy)
+        VALUES ($1, $2)
+        ON CONFLICT (guild_id) DO UPDATE
+            SET duplicate_policy = $2",
+        guild_id.0 as i64,
+        i16::from(policy)
+    )
+    .execute(database)
+    .await?;
+
+    ctx.say(format!("Duplicate policy set to {policy}")).await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only, category = "Admin")]
+pub async fn pagesize(
+    ctx: Context<'_>,
+    #[description = "Number of tracks per queue page"]
+    #[min = 5]
+    #[max = 25]
+    size: u8,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    let data = ctx.data();
+    let guild = data.guilds.get(guild_id).await?;
+    guild.lock().await.queue_page_size = size as usize;
+
+    let database = &data.database;
+
+    sqlx::query!(
+        "INSERT INTO guilds (guild_id, queue_page_size)
+        VALUES ($1, $2)
+        ON CONFLICT (guild_id) DO UPDATE
+            SET queue_page_size = $2",
+        guild_id.0 as i64,
+        size as i16
+    )
+    .execute(database)
+    .await?;
+
+    ctx.say(format!("The queue now shows {size} tracks per page"))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only, category = "Admin")]
+pub async fn autoplay(
+    ctx: Context<'_>,
+    #[description = "Keep playing related tracks when the queue ends"] enabled: bool,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    let data = ctx.data();
+    let guild = data.guilds.get(guild_id).await?;
+    guild.lock().await.autoplay = enabled;
+
+    let database = &data.database;
+
+    sqlx::query!(
+        "INSERT INTO guilds (guild_id, autoplay)
+        VALUES ($1, $2)
+        ON CONFLICT (guild_id) DO UPDATE
+            SET autoplay = $2",
+        guild_id.0 as i64,
+        enabled
+    )
+    .execute(database)
+    .await?;
+
+    if enabled {
+        ctx.say("Autoplay enabled, related tracks will be played when the queue ends")
+            .await?;
+    } else {
+        ctx.say("Autoplay disabled").await?;
+    }
+
+    Ok(())
+}
+
+#[poise::command(slash_command, owners_only, category = "Admin")]
+pub async fn crossfade(
+    ctx: Context<'_>,
+    #[description = "Fade length in seconds, 0 to disable"]
+    #[min = 0]
+    #[max = 12]
+    seconds: u8,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+
+    let data = ctx.data();
+    let guild = data.guilds.get(guild_id).await?;
+    guild.lock().await.crossfade = Duration::from_secs(seconds as u64);
+
+    let database = &data.database;
+
+    sqlx::query!(
+        "INSERT INTO guilds (guild_id, crossfade)
+        VALUES ($1, $2)
+        ON CONFLICT (guild_id) DO UPDATE
+            SET crossfade = $2",
+        guild_id.0 as i64,
+        seconds as i16
+    )
+    .execute(database)
+    .await?;
+
+    if seconds == 0 {
+        ctx.say("Crossfade disabled").await?;
+    } else {
+        ctx.say(format!(
+            "Tracks will crossfade over {} seconds, starting with the next track",
+            seconds
+        ))
+        .await?;
+    }
+
+    Ok(())
+}
@@ -1,21 +1,26 @@
 use crate::{
     error::Error,
+    interactions::{self, ComponentId},
     music::{
-        queue::{LoopModes, QueuedTrack},
-        utils,
+        crossfade, favourites,
+        filters::{AudioFilters, Preset},
+        history,
+        queue::{LoopModes, Placement, Queue, QueuedTrack},
+        saved, spotify,
+        utils::{self, Seek},
     },
+    shared_data::Data,
     Context,
 };
-use regex::Regex;
-use rspotify::{
-    clients::BaseClient,
-    model::{AlbumId, PlaylistId, TrackId},
-};
+use lavalink_rs::LavalinkClient;
 use serenity::{
-    builder::CreateEmbed, model::application::component::ButtonStyle,
-    model::application::interaction::InteractionResponseType,
+    builder::CreateEmbed, model::application::component::ButtonStyle, model::id::GuildId,
+    model::id::UserId, model::user::User, prelude::Mutex,
 };
-use std::time::Duration;
+use sqlx::PgPool;
+use std::{sync::Arc, time::Duration};
+
+const SEEK_STEP: Duration = Duration::from_secs(10);
 
 #[poise::command(slash_command, category = "Music")]
 pub async fn join(ctx: Context<'_>) -> Result<(), Error> {
@@ -29,6 +34,7 @@ pub async fn join(ctx: Context<'_>) -> Result<(), Error> {
 
     match channel_id {
         Some(id) => {
+            utils::voice_channel_check(&ctx, id).await?;
             utils::join(&ctx, guild.id, id, ctx.channel_id()).await?;
             ctx.say(format!("Joined <#{id}>")).await?;
         }
@@ -54,13 +60,15 @@ pub async fn leave(ctx: Context<'_>) -> Result<(), Error> {
 
     if has_handler {
         let data = ctx.data();
-        let queue = data.guilds.get_queue(guild.id).await;
+        let queue = data.guilds.get_queue(guild.id).await?;
         let mut queue_lock = queue.lock().await;
-        queue_lock.clean_up();
-        let lava = &data.lavalink;
-
-        lava.destroy(guild.id).await?;
-        manager.remove(guild.id).await?;
+        utils::disconnect(
+            ctx.serenity_context(),
+            &data.lavalink,
+            &mut queue_lock,
+            guild.id,
+        )
+        .await?;
     } else if guild.voice_states.get(&bot_id).is_some() {
         guild
             .member(ctx, bot_id)
@@ -79,49 +87,45 @@ pub async fn leave(ctx: Context<'_>) -> Result<(), Error> {
 #[poise::command(slash_command, category = "Music")]
 pub async fn play(
     ctx: Context<'_>,
-    #[description = "Name/link to a song"] mut query: String,
+    #[description = "Name/link to a song"] query: String,
 ) -> Result<(), Error> {
-    ctx.defer().await?;
-    let (lava, queue) = utils::voice_check(&ctx, true).await?;
-    let data = ctx.data();
-    if query.contains("open.spotify.com") {
-        let spotify = &data.spotify;
-        let reg =
-            Regex::new(r"^(https://open.spotify.com/)(playlist|album|track)/([a-zA-Z0-9]+)(.*)$")
-                .unwrap();
-        let capture = reg.captures(&query).unwrap();
-        if capture.len() < 3 {
-            return Err("Invalid spotify url".into());
-        }
-        if &capture[2] != "track" {
-            return Err("Use the `playlist` command to queue an album or a playlist".into());
-        }
-        let id = &capture[3];
-        let id = TrackId::from_id(id)?;
-        let track = spotify.track(id).await?;
-        query = format!("{} - {}", track.artists[0].name, track.name);
-    }
-    let mut query_result = lava.auto_search_tracks(query).await?;
+    enqueue_query(ctx, query, Placement::Back).await
+}
 
-    if query_result.tracks.is_empty() {
-        return Err("No matching videos found".into());
-    }
-    let track = query_result.tracks.remove(0);
-    let info = track.info.clone();
-    queue
-        .lock()
-        .await
-        .enqueue(QueuedTrack::new_initialized(track, ctx.author().id), lava)
-        .await?;
+#[poise::command(slash_command, category = "Music")]
+pub async fn playnext(
+    ctx: Context<'_>,
+    #[description = "Name/link to a song"] query: String,
+) -> Result<(), Error> {
+    enqueue_query(ctx, query, Placement::Next).await
+}
 
-    let title = info.map(|info| info.title);
+#[poise::command(slash_command, category = "Music")]
+pub async fn playnow(
+    ctx: Context<'_>,
+    #[description = "Name/link to a song"] query: String,
+) -> Result<(), Error> {
+    enqueue_query(ctx, query, Placement::Now).await
+}
+
+async fn enqueue_query(ctx: Context<'_>, query: String, placement: Placement) -> Result<(), Error> {
+    ctx.defer().await?;
+    let (lava, queue) = utils::voice_check(&ctx, true).await?;
+    let track = resolve_track(&ctx, &lava, query).await?;
+    let title = track.title.clone();
+    let duplicate = utils::enqueue_at(&ctx, &queue, track, placement, lava).await?;
 
     ctx.send(|m| {
         m.embed(|e| {
-            e.description(format!(
-                "{} added to the queue",
-                title.clone().unwrap_or_else(|| "Track".to_string())
-            ))
+            let mut description = match placement {
+                Placement::Back => format!("{} added to the queue", title),
+                Placement::Next => format!("{} will play next", title),
+                Placement::Now => format!("Playing {}", title),
+            };
+            if duplicate {
+                description += "\nThis track was already in the queue";
+            }
+            e.description(description)
         })
     })
     .await?;
@@ -129,99 +133,56 @@ pub async fn play(
     Ok(())
 }
 
+async fn resolve_track(
+    ctx: &Context<'_>,
+    lava: &LavalinkClient,
+    query: String,
+) -> Result<QueuedTrack, Error> {
+    if spotify::is_link(&query) {
+        let data = ctx.data();
+        let link = spotify::parse(&query).await?;
+        let mut track = spotify::track(&data.spotify, &link, ctx.author().id).await?;
+        track.init(lava, &data.database).await?;
+        return Ok(track);
+    }
+    let start_time = utils::start_time(&query);
+    let mut query_result = lava.auto_search_tracks(query).await?;
+
+    if query_result.tracks.is_empty() {
+        return Err("No matching videos found".into());
+    }
+    let mut track = QueuedTrack::new_initialized(query_result.tracks.remove(0), ctx.author().id);
+    if let Some(start_time) = start_time {
+        track.start_time = start_time.min(track.length);
+    }
+    Ok(track)
+}
+
 #[poise::command(slash_command, category = "Music")]
 pub async fn playlist(
     ctx: Context<'_>,
     #[description = "Playlist URL"] query: String,
 ) -> Result<(), Error> {
     ctx.defer().await?;
-    let mut tracks: Vec<QueuedTrack> = Vec::new();
-
     let (lava, queue) = utils::voice_check(&ctx, true).await?;
     let user_id = ctx.author().id;
-    if query.contains("open.spotify.com") {
-        let data = ctx.data();
-        let spotify = &data.spotify;
-        let reg =
-            Regex::new(r"^(https://open.spotify.com/)(playlist|album|track)/([a-zA-Z0-9]+)(.*)$")
-                .unwrap();
-        let capture = reg.captures(&query).unwrap();
-        if capture.len() < 3 {
-            return Err("Invalid spotify url".into());
-        }
-        if &capture[2] == "track" {
-            return Err("Use the `play` command to queue a single track".into());
-        }
-        let id = &capture[3];
-        let mut offset = 0;
-        if &capture[2] == "album" {
-            let limit = 50;
-            let id = AlbumId::from_id(id)?;
-            loop {
-                let album = spotify
-                    .album_track_manual(id.clone(), Some(limit), Some(offset))
-                    .await?;
-
-                for track in album.items {
-                    let title = track.name;
-                    let artist = track.artists[0].name.clone();
-                    let length = track.duration;
-                    let query = format!("{} - {}", &artist, &title);
-                    tracks.push(QueuedTrack::new(query, artist, length, user_id));
-                }
-
-                if album.next.is_none() {
-                    break;
-                }
-                offset += limit;
-            }
-        } else {
-            let limit = 100;
-            let id = PlaylistId::from_id(id)?;
-
-            loop {
-                let playlist = spotify
-                    .playlist_items_manual(id.clone(), None, None, Some(limit), Some(offset))
-                    .await?;
-
-                for item in playlist.items {
-                    if let Some(rspotify::model::PlayableItem::Track(track)) = item.track {
-                        let title = track.name;
-                        let artist = track.artists[0].name.clone();
-                        let length = track.duration;
-                        let query = format!("{} - {}", &artist, &title);
-                        tracks.push(QueuedTrack::new(query, artist, length, user_id));
-                    }
-                }
-
-                if playlist.next.is_none() {
-                    break;
-                }
-                offset += limit;
-            }
-        }
+    let tracks = if spotify::is_link(&query) {
+        let link = spotify::parse(&query).await?;
+        spotify::tracks(&ctx.data().spotify, &link, user_id).await?
     } else {
         let query_result = lava.get_tracks(query).await?;
-        for track in query_result.tracks {
-            tracks.push(QueuedTrack::new_initialized(track, user_id));
-        }
-    }
+        query_result
+            .tracks
+            .into_iter()
+            .map(|track| QueuedTrack::new_initialized(track, user_id))
+            .collect()
+    };
 
     if tracks.is_empty() {
         return Err("No matching videos found".into());
     }
-    let amount = tracks.len();
-    if queue
-        .lock()
-        .await
-        .enqueue_multiple(tracks, lava)
-        .await
-        .is_err()
-    {
-        return Err("Error queuing the tracks".into());
-    }
-
-    ctx.send(|m| m.embed(|e| e.description(format!("Added {} tracks to the queue", amount))))
+    let description = utils::enqueue_multiple(&ctx, &queue, tracks, lava).await?;
+    ctx.send(|m| m.embed(|e| e.description(description)))
         .await?;
 
     Ok(())
@@ -233,7 +194,7 @@ pub async fn search(
     #[description = "Search query"] query: String,
 ) -> Result<(), Error> {
     ctx.defer().await?;
-    let (lava, queue) = utils::voice_check(&ctx, true).await?;
+    let (lava, _) = utils::voice_check(&ctx, true).await?;
     let mut query_result = lava.search_tracks(query).await?;
 
     if query_result.tracks.is_empty() {
@@ -255,57 +216,25 @@ pub async fn search(
         );
     }
 
-    let uuid = ctx.id() as usize;
-    let handle = ctx
-        .send(|m| {
-            m.embed(|e| e.title("Search results").description(results))
-                .components(|c| {
-                    c.create_action_row(|r| {
-                        for i in 0..query_result.tracks.len() {
-                            r.create_button(|b| {
-                                b.style(ButtonStyle::Primary)
-                                    .label(i + 1)
-                                    .custom_id(uuid + i)
-                            });
-                        }
-                        r
-                    })
-                })
-        })
-        .await?;
-    let msg = handle.message().await?;
+    let guild_id = ctx.guild_id().unwrap();
     let user_id = ctx.author().id;
-
-    if let Some(mci) = serenity::collector::CollectComponentInteraction::new(ctx)
-        .author_id(user_id)
-        .message_id(msg.id)
-        .collect_limit(1)
-        .timeout(Duration::from_secs(30))
-        .await
-    {
-        let choice = mci.data.custom_id.parse::<usize>().unwrap() - uuid;
-        let track = query_result.tracks.remove(choice);
-        let info = track.info.clone();
-        let title = info.map(|info| info.title);
-        queue
-            .lock()
-            .await
-            .enqueue(QueuedTrack::new_initialized(track, user_id), lava)
-            .await?;
-
-        mci.create_interaction_response(ctx, |r| {
-            r.kind(InteractionResponseType::UpdateMessage)
-                .interaction_response_data(|d| {
-                    d.content(format!(
-                        "{} added to queue.",
-                        title.unwrap_or_else(|| "Track".to_string())
-                    ))
-                    .components(|c| c.set_action_rows(Vec::default()))
-                    .set_embeds([])
+    ctx.send(|m| {
+        m.embed(|e| e.title("Search results").description(results))
+            .components(|c| {
+                c.create_action_row(|r| {
+                    for (i, track) in query_result.tracks.iter().enumerate() {
+                        let identifier = track.info.as_ref().unwrap().identifier.clone();
+                        r.create_button(|b| {
+                            b.style(ButtonStyle::Primary)
+                                .label(i + 1)
+                                .custom_id(ComponentId::Search(guild_id, user_id, identifier))
+                        });
+                    }
+                    r
                 })
-        })
-        .await?;
-    }
+            })
+    })
+    .await?;
 
     Ok(())
 }
@@ -315,7 +244,12 @@ pub async fn nowplaying(ctx: Context<'_>) -> Result<(), Error> {
     let guild_id = ctx.guild_id().unwrap();
     let data = ctx.data();
 
-    let queue = data.guilds.get_queue(guild_id).await;
+    let (vote_skip, threshold) = {
+        let guild = data.guilds.get(guild_id).await?;
+        let guild_lock = guild.lock().await;
+        (guild_lock.vote_skip, guild_lock.vote_skip_threshold)
+    };
+    let queue = data.guilds.get_queue(guild_id).await?;
     let queue_lock = queue.lock().await;
     let lava = &data.lavalink;
     let nodes = lava.nodes().await;
@@ -337,9 +271,7 @@ pub async fn nowplaying(ctx: Context<'_>) -> Result<(), Error> {
             let requester_id = queue_lock.current_track.clone().unwrap().requester;
             let requester = ctx.serenity_context().cache.member(guild_id, requester_id);
 
-            let bar1 = ((info.position as f32 / info.length as f32) * 19.) as usize;
-            let bar2 = 19 - bar1;
-            let progress_bar = "▬".repeat(bar1) + "🔘" + &"▬".repeat(bar2);
+            let progress_bar = utils::progress_bar(info.position, info.length);
 
             embed
                 .title(title)
@@ -362,6 +294,16 @@ pub async fn nowplaying(ctx: Context<'_>) -> Result<(), Error> {
                         f.text(format!("Requested by {}", requester_id.0))
                     }
                 });
+
+            if vote_skip {
+                let listeners = utils::bot_listeners(&ctx);
+                let votes = queue_lock.skip_votes(&listeners);
+                let required = utils::required_votes(listeners.len(), threshold);
+                embed.field("Skip votes", format!("{votes}/{required}"), true);
+            }
+            if let Some(filters) = queue_lock.filters.description() {
+                embed.field("Filters", filters, false);
+            }
         }
     }
 
@@ -380,25 +322,72 @@ pub async fn queue(
     #[min = 1]
     page: Option<usize>,
 ) -> Result<(), Error> {
-    let mut page = page.unwrap_or(1);
-    let mut page_count = 1;
     let guild_id = ctx.guild_id().unwrap();
-    let data = ctx.data();
-    let queue = data.guilds.get_queue(guild_id).await;
+    let (embed, page, page_count, tracks) =
+        queue_embed(ctx.data(), guild_id, None, page.unwrap_or(1) - 1).await?;
+    ctx.send(|m| {
+        m.embeds.push(embed);
+        m.components(|c| interactions::queue_components(c, guild_id, page, page_count, &tracks))
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, category = "Music")]
+pub async fn myqueue(
+    ctx: Context<'_>,
+    #[description = "Page"]
+    #[min = 1]
+    page: Option<usize>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let user_id = ctx.author().id;
+    let (embed, page, page_count, _) =
+        queue_embed(ctx.data(), guild_id, Some(user_id), page.unwrap_or(1) - 1).await?;
+    ctx.send(|m| {
+        m.embeds.push(embed);
+        m.components(|c| {
+            interactions::pagination(c, page, page_count, |page| {
+                ComponentId::UserQueue(guild_id, user_id, page)
+            })
+        })
+    })
+    .await?;
 
+    Ok(())
+}
+
+pub async fn queue_embed(
+    data: &Data,
+    guild_id: GuildId,
+    user: Option<UserId>,
+    page: usize,
+) -> Result<(CreateEmbed, usize, usize, Vec<(usize, u64, String)>), Error> {
+    let page_size = data
+        .guilds
+        .get(guild_id)
+        .await?
+        .lock()
+        .await
+        .queue_page_size;
+    let queue = data.guilds.get_queue(guild_id).await?;
     let queue_lock = queue.lock().await;
-    let (tracklist, info) = queue_lock.tracklist(page - 1);
-    drop(queue_lock);
+    let (tracklist, info) = match user {
+        Some(user) => queue_lock.user_tracklist(user, page, page_size),
+        None => queue_lock.tracklist(page, page_size),
+    };
 
     let mut embed = CreateEmbed::default();
     embed.title("Queue").description(tracklist);
+    let (mut page, mut page_count) = (0, 1);
     if let Some((page_ret, page_count_ret, track_count, length)) = info {
-        page = page_ret + 1;
+        page = page_ret;
         page_count = page_count_ret;
         embed.footer(|f| {
             f.text(format!(
                 "Page {}/{} | Total queue length: {} {} ({})",
-                page,
+                page + 1,
                 page_count,
                 track_count,
                 if track_count == 1 { "track" } else { "tracks" },
@@ -406,174 +395,357 @@ pub async fn queue(
             ))
         });
     }
+    // only the whole queue can be changed from the menus
+    let tracks = match user {
+        Some(_) => Vec::new(),
+        None => queue_lock
+            .page_tracks(page, page_size)
+            .into_iter()
+            .map(|(index, track)| (index, track.id, track.display_title()))
+            .collect(),
+    };
 
-    let uuid = ctx.id() as usize;
-    let handle = ctx
-        .send(|m| {
-            m.embeds.push(embed);
-            if page_count > 1 {
-                m.components(|c| {
-                    c.create_action_row(|r| {
-                        r.create_button(|b| {
-                            b.style(ButtonStyle::Primary).label("<").custom_id(uuid)
-                        });
-                        r.create_button(|b| {
-                            b.style(ButtonStyle::Primary).label(">").custom_id(uuid + 1)
-                        });
-                        r
-                    })
-                });
-            }
-            m
-        })
-        .await?;
-    let msg = handle.message().await?;
-    let user_id = ctx.author().id;
+    Ok((embed, page, page_count, tracks))
+}
 
-    while let Some(mci) = serenity::collector::CollectComponentInteraction::new(ctx)
-        .author_id(user_id)
-        .message_id(msg.id)
-        .timeout(Duration::from_secs(30))
-        .await
-    {
-        let choice = mci.data.custom_id.parse::<usize>().unwrap() - uuid;
-        if choice == 0 {
-            if page >= 2 {
-                page -= 1;
-            }
-        } else {
-            page += 1
+#[poise::command(slash_command, category = "Music")]
+pub async fn history(
+    ctx: Context<'_>,
+    #[description = "Page"]
+    #[min = 1]
+    page: Option<usize>,
+    #[description = "Number of the entry to add to the queue"]
+    #[min = 1]
+    enqueue: Option<usize>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let data = ctx.data();
+
+    if let Some(index) = enqueue {
+        ctx.defer().await?;
+        let entry = match history::entries(&data.database, guild_id, index - 1, 1)
+            .await?
+            .pop()
+        {
+            Some(entry) => entry,
+            None => return Err("Index out of range".into()),
         };
+        let (lava, queue) = utils::voice_check(&ctx, true).await?;
+        let mut query_result = lava.auto_search_tracks(&entry.uri).await?;
+        if query_result.tracks.is_empty() {
+            return Err("No matching videos found".into());
+        }
+        let track = QueuedTrack::new_initialized(query_result.tracks.remove(0), ctx.author().id);
+        let title = track.title.clone();
+        utils::enqueue(&ctx, &queue, track, lava).await?;
 
-        let queue_lock = queue.lock().await;
-        let (tracklist, info) = queue_lock.tracklist(page - 1);
-        drop(queue_lock);
-
-        let mut embed = CreateEmbed::default();
-        embed.title("Queue").description(tracklist);
-        if let Some((page_ret, page_count_ret, track_count, length)) = info {
-            page = page_ret + 1;
-            page_count = page_count_ret;
-            embed.footer(|f| {
-                f.text(format!(
-                    "Page {}/{} | Total queue length: {} {} ({})",
-                    page,
-                    page_count,
-                    track_count,
-                    if track_count == 1 { "track" } else { "tracks" },
-                    utils::length_to_string(length.as_secs())
-                ))
-            });
+        ctx.send(|m| m.embed(|e| e.description(format!("{} added to the queue", title))))
+            .await?;
+        return Ok(());
+    }
 
-            mci.create_interaction_response(ctx, |r| {
-                r.kind(InteractionResponseType::UpdateMessage)
-                    .interaction_response_data(|d| d.set_embeds([embed]))
+    let (embed, page, page_count) =
+        history_embed(&data.database, guild_id, page.unwrap_or(1) - 1).await?;
+    ctx.send(|m| {
+        m.embeds.push(embed);
+        m.components(|c| {
+            interactions::pagination(c, page, page_count, |page| {
+                ComponentId::History(guild_id, page)
             })
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+pub async fn history_embed(
+    database: &PgPool,
+    guild_id: GuildId,
+    mut page: usize,
+) -> Result<(CreateEmbed, usize, usize), Error> {
+    let count = history::count(database, guild_id).await?;
+    let page_count = ((count as f32 / history::PAGE_SIZE as f32).ceil() as usize).max(1);
+    if page > page_count - 1 {
+        page = page_count - 1;
+    }
+
+    let offset = page * history::PAGE_SIZE;
+    let entries = history::entries(database, guild_id, offset, history::PAGE_SIZE).await?;
+    let mut description = String::new();
+    for (i, entry) in entries.iter().enumerate() {
+        description += &format!(
+            "{}. [{}]({}) - <@{}> <t:{}:R>{}\n",
+            offset + i + 1,
+            entry.title,
+            entry.uri,
+            entry.requester.0,
+            entry.started_at,
+            if entry.skipped { " (skipped)" } else { "" }
+        );
+    }
+    if description.is_empty() {
+        description += "Nothing has been played yet.";
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("History")
+        .description(description)
+        .footer(|f| f.text(format!("Page {}/{}", page + 1, page_count)));
+
+    Ok((embed, page, page_count))
+}
+
+#[poise::command(slash_command, category = "Music")]
+pub async fn like(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let data = ctx.data();
+    let queue = data.guilds.get_queue(guild_id).await?;
+    let track = match queue.lock().await.current_track.clone() {
+        Some(track) => track,
+        None => return Err("No track currently playing".into()),
+    };
+
+    if favourites::add(&data.database, ctx.author().id, &track).await? {
+        ctx.say(format!("Added {} to your favourites", track.title))
+            .await?;
+    } else {
+        ctx.say(format!("{} is already in your favourites", track.title))
             .await?;
-        }
     }
 
     Ok(())
 }
 
 #[poise::command(slash_command, category = "Music")]
-pub async fn myqueue(
+pub async fn favourites(
     ctx: Context<'_>,
     #[description = "Page"]
     #[min = 1]
     page: Option<usize>,
+    #[description = "Number of the favourite to add to the queue"]
+    #[min = 1]
+    enqueue: Option<usize>,
+    #[description = "Add all favourites to the queue"] all: Option<bool>,
 ) -> Result<(), Error> {
-    let mut page = page.unwrap_or(1);
-    let mut page_count = 1;
-    let guild_id = ctx.guild_id().unwrap();
     let data = ctx.data();
-    let queue = data.guilds.get_queue(guild_id).await;
+    let user_id = ctx.author().id;
 
-    let queue_lock = queue.lock().await;
-    let (tracklist, info) = queue_lock.user_tracklist(ctx.author().id, page - 1);
-    drop(queue_lock);
+    if all == Some(true) {
+        ctx.defer().await?;
+        let count = favourites::count(&data.database, user_id).await?;
+        let tracks = favourites::entries(&data.database, user_id, 0, count).await?;
+        if tracks.is_empty() {
+            return Err("You don't have any favourites yet".into());
+        }
+        let (lava, queue) = utils::voice_check(&ctx, true).await?;
+        let description = utils::enqueue_multiple(&ctx, &queue, tracks, lava).await?;
+        ctx.send(|m| m.embed(|e| e.description(description)))
+            .await?;
+        return Ok(());
+    }
 
-    let mut embed = CreateEmbed::default();
-    embed.title("Queue").description(tracklist);
-    if let Some((page_ret, page_count_ret, track_count, length)) = info {
-        page = page_ret + 1;
-        page_count = page_count_ret;
-        embed.footer(|f| {
-            f.text(format!(
-                "Page {}/{} | Total queue length: {} {} ({})",
-                page,
-                page_count,
-                track_count,
-                if track_count == 1 { "track" } else { "tracks" },
-                utils::length_to_string(length.as_secs())
-            ))
-        });
+    if let Some(index) = enqueue {
+        ctx.defer().await?;
+        let track = match favourites::entries(&data.database, user_id, index - 1, 1)
+            .await?
+            .pop()
+        {
+            Some(track) => track,
+            None => return Err("Index out of range".into()),
+        };
+        let (lava, queue) = utils::voice_check(&ctx, true).await?;
+        let title = track.title.clone();
+        let duplicate = utils::enqueue(&ctx, &queue, track, lava).await?;
+
+        let mut description = format!("{} added to the queue", title);
+        if duplicate {
+            description += "\nThis track was already in the queue";
+        }
+        ctx.send(|m| m.embed(|e| e.description(description)))
+            .await?;
+        return Ok(());
     }
 
-    let uuid = ctx.id() as usize;
-    let handle = ctx
-        .send(|m| {
-            m.embeds.push(embed);
-            if page_count > 1 {
-                m.components(|c| {
-                    c.create_action_row(|r| {
-                        r.create_button(|b| {
-                            b.style(ButtonStyle::Primary).label("<").custom_id(uuid)
-                        });
-                        r.create_button(|b| {
-                            b.style(ButtonStyle::Primary).label(">").custom_id(uuid + 1)
-                        });
-                        r
-                    })
-                });
-            }
-            m
+    let guild_id = ctx.guild_id().unwrap();
+    let (embed, page, page_count) =
+        favourites_embed(&data.database, user_id, page.unwrap_or(1) - 1).await?;
+    ctx.send(|m| {
+        m.embeds.push(embed);
+        m.components(|c| {
+            interactions::pagination(c, page, page_count, |page| {
+                ComponentId::Favourites(guild_id, user_id, page)
+            })
         })
+    })
+    .await?;
+
+    Ok(())
+}
+
+pub async fn favourites_embed(
+    database: &PgPool,
+    user_id: UserId,
+    mut page: usize,
+) -> Result<(CreateEmbed, usize, usize), Error> {
+    let count = favourites::count(database, user_id).await?;
+    let page_count = ((count as f32 / favourites::PAGE_SIZE as f32).ceil() as usize).max(1);
+    if page > page_count - 1 {
+        page = page_count - 1;
+    }
+
+    let offset = page * favourites::PAGE_SIZE;
+    let tracks = favourites::entries(database, user_id, offset, favourites::PAGE_SIZE).await?;
+    let mut description = String::new();
+    for (i, track) in tracks.iter().enumerate() {
+        description += &format!(
+            "{}. {} ({})\n",
+            offset + i + 1,
+            track.title,
+            utils::length_to_string(track.length.as_secs())
+        );
+    }
+    if description.is_empty() {
+        description += "You don't have any favourites yet.";
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Favourites")
+        .description(description)
+        .footer(|f| f.text(format!("Page {}/{}", page + 1, page_count)));
+
+    Ok((embed, page, page_count))
+}
+
+#[poise::command(
+    slash_command,
+    category = "Music",
+    subcommands(
+        "saved_save",
+        "saved_load",
+        "saved_list",
+        "saved_delete",
+        "saved_share"
+    )
+)]
+pub async fn saved(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "save", category = "Music")]
+pub async fn saved_save(
+    ctx: Context<'_>,
+    #[description = "Playlist name"] name: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let data = ctx.data();
+    let queue = data.guilds.get_queue(guild_id).await?;
+    let tracks = queue.lock().await.all_tracks();
+    if tracks.is_empty() {
+        return Err("The queue is empty".into());
+    }
+
+    saved::save(&data.database, ctx.author().id, &name, &tracks).await?;
+    ctx.say(format!("Saved {} tracks as {}", tracks.len(), name))
         .await?;
-    let msg = handle.message().await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "load", category = "Music")]
+pub async fn saved_load(
+    ctx: Context<'_>,
+    #[description = "Playlist name"] name: String,
+    #[description = "Owner of a shared playlist"] user: Option<User>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
     let user_id = ctx.author().id;
+    let owner = user.map_or(user_id, |user| user.id);
+    let tracks = match saved::load(&ctx.data().database, owner, &name, user_id).await? {
+        Some(tracks) if !tracks.is_empty() => tracks,
+        Some(_) => return Err("This playlist is empty".into()),
+        None => return Err(Error::Command(format!("No saved playlist named {name}"))),
+    };
 
-    while let Some(mci) = serenity::collector::CollectComponentInteraction::new(ctx)
-        .author_id(user_id)
-        .message_id(msg.id)
-        .timeout(Duration::from_secs(30))
-        .await
-    {
-        let choice = mci.data.custom_id.parse::<usize>().unwrap() - uuid;
-        if choice == 0 {
-            if page >= 2 {
-                page -= 1;
-            }
-        } else {
-            page += 1
-        };
+    let (lava, queue) = utils::voice_check(&ctx, true).await?;
+    let description = utils::enqueue_multiple(&ctx, &queue, tracks, lava).await?;
+    ctx.send(|m| m.embed(|e| e.description(description)))
+        .await?;
 
-        let queue_lock = queue.lock().await;
-        let (tracklist, info) = queue_lock.user_tracklist(user_id, page - 1);
-        drop(queue_lock);
-
-        let mut embed = CreateEmbed::default();
-        embed.title("Queue").description(tracklist);
-        if let Some((page_ret, page_count_ret, track_count, length)) = info {
-            page = page_ret + 1;
-            page_count = page_count_ret;
-            embed.footer(|f| {
-                f.text(format!(
-                    "Page {}/{} | Total queue length: {} {} ({})",
-                    page,
-                    page_count,
-                    track_count,
-                    if track_count == 1 { "track" } else { "tracks" },
-                    utils::length_to_string(length.as_secs())
-                ))
-            });
+    Ok(())
+}
 
-            mci.create_interaction_response(ctx, |r| {
-                r.kind(InteractionResponseType::UpdateMessage)
-                    .interaction_response_data(|d| d.set_embeds([embed]))
+#[poise::command(slash_command, rename = "list", category = "Music")]
+pub async fn saved_list(
+    ctx: Context<'_>,
+    #[description = "Show the shared playlists of this user"] user: Option<User>,
+) -> Result<(), Error> {
+    let user_id = ctx.author().id;
+    let owner = user.map_or(user_id, |user| user.id);
+    let mut playlists = saved::list(&ctx.data().database, owner).await?;
+    if owner != user_id {
+        playlists.retain(|playlist| playlist.shared);
+    }
+
+    let description = if playlists.is_empty() {
+        "No saved playlists.".to_string()
+    } else {
+        playlists
+            .iter()
+            .map(|playlist| {
+                format!(
+                    "{} ({} {}){}",
+                    playlist.name,
+                    playlist.track_count,
+                    if playlist.track_count == 1 {
+                        "track"
+                    } else {
+                        "tracks"
+                    },
+                    if playlist.shared { " - shared" } else { "" }
+                )
             })
-            .await?;
-        }
+            .collect::<Vec<String>>()
+            .join("\n")
+    };
+    ctx.send(|m| m.embed(|e| e.title("Saved playlists").description(description)))
+        .await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "delete", category = "Music")]
+pub async fn saved_delete(
+    ctx: Context<'_>,
+    #[description = "Playlist name"] name: String,
+) -> Result<(), Error> {
+    if !saved::delete(&ctx.data().database, ctx.author().id, &name).await? {
+        return Err(Error::Command(format!("No saved playlist named {name}")));
+    }
+    ctx.say(format!("Deleted {name}")).await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "share", category = "Music")]
+pub async fn saved_share(
+    ctx: Context<'_>,
+    #[description = "Playlist name"] name: String,
+    #[description = "Whether other users can load the playlist"] shared: bool,
+) -> Result<(), Error> {
+    if !saved::set_shared(&ctx.data().database, ctx.author().id, &name, shared).await? {
+        return Err(Error::Command(format!("No saved playlist named {name}")));
+    }
+    if shared {
+        ctx.say(format!(
+            "{name} is now shared, others can load it with `/saved load {name} <@{}>`",
+            ctx.author().id.0
+        ))
+        .await?;
+    } else {
+        ctx.say(format!("{name} is no longer shared")).await?;
     }
 
     Ok(())
@@ -583,9 +755,9 @@ pub async fn myqueue(
 pub async fn clear(ctx: Context<'_>) -> Result<(), Error> {
     let guild_id = ctx.guild_id().unwrap();
 
-    let queue = ctx.data().guilds.get_queue(guild_id).await;
+    let queue = ctx.data().guilds.get_queue(guild_id).await?;
     let mut queue_lock = queue.lock().await;
-    queue_lock.clear(ctx.author().id);
+    queue_lock.clear(ctx.author().id).await;
     ctx.say("Queue cleared").await?;
 
     Ok(())
@@ -605,26 +777,65 @@ pub async fn stop(ctx: Context<'_>) -> Result<(), Error> {
 #[poise::command(slash_command, category = "Music")]
 pub async fn remove(
     ctx: Context<'_>,
-    #[description = "Index"]
-    #[min = 1]
-    index: usize,
+    #[description = "Positions in /queue to remove, like 3, 3-7 or 1,4,9"] tracks: Option<String>,
+    #[description = "Remove all tracks requested by this user"] user: Option<User>,
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().unwrap();
+    let author = ctx.author().id;
+    let is_dj = utils::is_dj(&ctx).await?;
 
-    let queue = ctx.data().guilds.get_queue(guild_id).await;
-    let mut queue_lock = queue.lock().await;
-    match queue_lock.remove(index - 1, ctx.author().id) {
-        Some(track) => {
-            ctx.send(|m| {
-                m.embed(|e| {
-                    e.description(format!("{} has been removed from the queue", &track.title))
-                })
+    let queue = ctx.data().guilds.get_queue(guild_id).await?;
+    if let Some(user) = user {
+        if !is_dj && user.id != author {
+            return Err("You can only remove tracks you requested".into());
+        }
+        let removed = queue.lock().await.remove_user(user.id).await;
+        ctx.send(|m| {
+            m.embed(|e| {
+                e.description(format!(
+                    "Removed {} {} requested by <@{}>",
+                    removed,
+                    if removed == 1 { "track" } else { "tracks" },
+                    user.id.0
+                ))
             })
-            .await?;
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let ranges = match tracks {
+        Some(tracks) => utils::parse_ranges(&tracks)?,
+        None => return Err("Give the positions of the tracks or a user".into()),
+    };
+    let mut queue_lock = queue.lock().await;
+    if ranges
+        .iter()
+        .any(|range| *range.end() > queue_lock.track_count())
+    {
+        return Err("Index out of range".into());
+    }
+    let mut indices: Vec<usize> = ranges.into_iter().flatten().map(|i| i - 1).collect();
+    indices.sort_unstable();
+    indices.dedup();
+    if !is_dj {
+        for index in &indices {
+            if let Some(track) = queue_lock.track_at(*index) {
+                if track.requester != author {
+                    return Err("You can only remove tracks you requested".into());
+                }
+            }
         }
-        None => return Err("Index out of range".into()),
     }
 
+    let removed = queue_lock.remove_multiple(&indices).await;
+    let description = match removed.as_slice() {
+        [track] => format!("{} has been removed from the queue", &track.title),
+        _ => format!("{} tracks have been removed from the queue", removed.len()),
+    };
+    ctx.send(|m| m.embed(|e| e.description(description)))
+        .await?;
+
     Ok(())
 }
 
@@ -640,10 +851,21 @@ pub async fn mv(
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().unwrap();
     let to = to.unwrap_or(1);
+    let is_dj = utils::is_dj(&ctx).await?;
 
-    let queue = ctx.data().guilds.get_queue(guild_id).await;
+    let queue = ctx.data().guilds.get_queue(guild_id).await?;
     let mut queue_lock = queue.lock().await;
-    match queue_lock.move_track(from - 1, to - 1, ctx.author().id) {
+    if !is_dj && !queue_lock.round_robin() {
+        if let Some(track) = queue_lock.get(from - 1) {
+            if track.requester != ctx.author().id {
+                return Err("You can only move tracks you requested".into());
+            }
+        }
+    }
+    match queue_lock
+        .move_track(from - 1, to - 1, ctx.author().id)
+        .await
+    {
         Some(track) => {
             ctx.send(|m| {
                 m.embed(|e| {
@@ -672,10 +894,23 @@ pub async fn swap(
     second: usize,
 ) -> Result<(), Error> {
     let guild_id = ctx.guild_id().unwrap();
+    let is_dj = utils::is_dj(&ctx).await?;
 
-    let queue = ctx.data().guilds.get_queue(guild_id).await;
+    let queue = ctx.data().guilds.get_queue(guild_id).await?;
     let mut queue_lock = queue.lock().await;
-    match queue_lock.swap(first - 1, second - 1, ctx.author().id) {
+    if !is_dj && !queue_lock.round_robin() {
+        for index in [first, second] {
+            if let Some(track) = queue_lock.get(index - 1) {
+                if track.requester != ctx.author().id {
+                    return Err("You can only swap tracks you requested".into());
+                }
+            }
+        }
+    }
+    match queue_lock
+        .swap(first - 1, second - 1, ctx.author().id)
+        .await
+    {
         Some((first, second)) => {
             ctx.send(|m| {
                 m.embed(|e| {
@@ -696,8 +931,58 @@ pub async fn swap(
 #[poise::command(slash_command, category = "Music")]
 pub async fn skip(ctx: Context<'_>) -> Result<(), Error> {
     let (lava, queue) = utils::voice_check(&ctx, false).await?;
-    queue.lock().await.skip(lava).await?;
-    ctx.say("Track skipped").await?;
+    let caller = utils::Caller::from_command(&ctx).unwrap();
+    let message = utils::skip(&caller, lava, &queue).await?;
+    ctx.say(message).await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, category = "Music")]
+pub async fn skipto(
+    ctx: Context<'_>,
+    #[description = "Index"]
+    #[min = 1]
+    index: usize,
+) -> Result<(), Error> {
+    let (lava, queue) = utils::voice_check(&ctx, false).await?;
+    let mut queue_lock = queue.lock().await;
+    if queue_lock.current_track.is_none() {
+        return Err("No track currently playing".into());
+    }
+    match queue_lock.skip_to(index - 1, lava).await? {
+        Some(track) => {
+            ctx.say(format!("Skipped to {}", track.title)).await?;
+        }
+        None => return Err("Index out of range".into()),
+    }
+
+    Ok(())
+}
+
+#[poise::command(slash_command, category = "Music")]
+pub async fn previous(ctx: Context<'_>) -> Result<(), Error> {
+    let (lava, queue) = utils::voice_check(&ctx, false).await?;
+    let mut queue_lock = queue.lock().await;
+    match queue_lock.previous(lava).await? {
+        Some(track) => {
+            ctx.say(format!("Playing previous track: {}", track.title))
+                .await?;
+        }
+        None => return Err("There is no previous track".into()),
+    }
+
+    Ok(())
+}
+
+#[poise::command(slash_command, category = "Music")]
+pub async fn replay(ctx: Context<'_>) -> Result<(), Error> {
+    let (lava, queue) = utils::voice_check(&ctx, false).await?;
+    if queue.lock().await.current_track.is_none() {
+        return Err("No track currently playing".into());
+    }
+    seek_track(ctx, &lava, &queue, Duration::ZERO).await?;
+    ctx.say("Track restarted").await?;
 
     Ok(())
 }
@@ -706,7 +991,7 @@ pub async fn skip(ctx: Context<'_>) -> Result<(), Error> {
 pub async fn shuffle(ctx: Context<'_>) -> Result<(), Error> {
     let (_, queue) = utils::voice_check(&ctx, false).await?;
     let mut queue_lock = queue.lock().await;
-    queue_lock.shuffle(ctx.author().id);
+    queue_lock.shuffle(ctx.author().id).await;
     ctx.say("Queue shuffled").await?;
 
     Ok(())
@@ -715,13 +1000,171 @@ pub async fn shuffle(ctx: Context<'_>) -> Result<(), Error> {
 #[poise::command(slash_command, category = "Music")]
 pub async fn seek(
     ctx: Context<'_>,
-    #[description = "Time in seconds"] time: u64,
+    #[description = "Time, e.g. 90, 1:30, 1:02:30, +30 or -1:00"] time: String,
 ) -> Result<(), Error> {
-    let position = Duration::from_secs(time);
+    let seek = utils::parse_seek(&time)?;
+    seek_to(ctx, seek).await
+}
+
+#[poise::command(slash_command, category = "Music")]
+pub async fn forward(
+    ctx: Context<'_>,
+    #[description = "Time to skip, e.g. 30 or 1:00"] time: Option<String>,
+) -> Result<(), Error> {
+    let time = match time {
+        Some(time) => utils::parse_time(&time).ok_or("Invalid time")?,
+        None => SEEK_STEP,
+    };
+    seek_to(ctx, Seek::Forward(time)).await
+}
+
+#[poise::command(slash_command, category = "Music")]
+pub async fn rewind(
+    ctx: Context<'_>,
+    #[description = "Time to go back, e.g. 30 or 1:00"] time: Option<String>,
+) -> Result<(), Error> {
+    let time = match time {
+        Some(time) => utils::parse_time(&time).ok_or("Invalid time")?,
+        None => SEEK_STEP,
+    };
+    seek_to(ctx, Seek::Back(time)).await
+}
+
+async fn seek_to(ctx: Context<'_>, seek: Seek) -> Result<(), Error> {
     let guild_id = ctx.guild_id().unwrap();
-    let (lava, _) = utils::voice_check(&ctx, false).await?;
+    let (lava, queue) = utils::voice_check(&ctx, false).await?;
+    let length = match &queue.lock().await.current_track {
+        Some(track) => track.length,
+        None => return Err("Nothing is playing".into()),
+    };
+    let current = current_position(&lava, guild_id).await;
+
+    let position = seek.position(current, length);
+    seek_track(ctx, &lava, &queue, position).await?;
+    ctx.say(format!(
+        "Seeked to {}/{}",
+        utils::length_to_string(position.as_secs()),
+        utils::length_to_string(length.as_secs())
+    ))
+    .await?;
+
+    Ok(())
+}
+
+// also starts the crossfade of the track over from the new position
+async fn seek_track(
+    ctx: Context<'_>,
+    lava: &LavalinkClient,
+    queue: &Arc<Mutex<Queue>>,
+    position: Duration,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let crossfade = ctx
+        .data()
+        .guilds
+        .get(guild_id)
+        .await?
+        .lock()
+        .await
+        .crossfade;
     lava.seek(guild_id, position).await?;
-    ctx.say("Done").await?;
+
+    let mut queue_lock = queue.lock().await;
+    queue_lock.save_position(position).await;
+    crossfade::restart(
+        lava,
+        queue,
+        &mut queue_lock,
+        guild_id,
+        crossfade,
+        Some(position),
+    )
+    .await;
+
+    Ok(())
+}
+
+async fn current_position(lava: &LavalinkClient, guild_id: GuildId) -> Duration {
+    let position = lava
+        .nodes()
+        .await
+        .get(&guild_id.0)
+        .and_then(|node| node.now_playing.as_ref())
+        .and_then(|track| track.track.info.as_ref())
+        .map_or(0, |info| info.position);
+    Duration::from_millis(position)
+}
+
+#[poise::command(slash_command, category = "Music")]
+pub async fn chapters(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let (lava, queue) = utils::voice_check(&ctx, false).await?;
+    let position = current_position(&lava, guild_id).await;
+
+    let queue_lock = queue.lock().await;
+    let track = queue_lock
+        .current_track
+        .as_ref()
+        .ok_or("Nothing is playing")?;
+    let segments = match &queue_lock.segments {
+        Some(segments) if !segments.chapters.is_empty() => segments,
+        _ => return Err("No chapters available for this track".into()),
+    };
+    let current = segments.chapter_at(position);
+    let mut description = String::new();
+    for (i, chapter) in segments.chapters.iter().enumerate() {
+        let line = format!(
+            "{}. {} ({} - {})",
+            i + 1,
+            chapter.title,
+            utils::length_to_string(chapter.start.as_secs()),
+            utils::length_to_string(chapter.end.as_secs())
+        );
+        if current == Some(i) {
+            description += &format!("**{}**\n", line);
+        } else {
+            description += &format!("{}\n", line);
+        }
+    }
+    let title = track.title.clone();
+    drop(queue_lock);
+
+    ctx.send(|m| {
+        m.embed(|e| {
+            e.author(|a| a.name("Chapters"))
+                .title(title)
+                .description(description)
+        })
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[poise::command(slash_command, category = "Music")]
+pub async fn nextchapter(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let (lava, queue) = utils::voice_check(&ctx, false).await?;
+    let position = current_position(&lava, guild_id).await;
+
+    let queue_lock = queue.lock().await;
+    let chapter = match &queue_lock.segments {
+        Some(segments) if !segments.chapters.is_empty() => segments
+            .chapters
+            .iter()
+            .find(|chapter| chapter.start > position)
+            .ok_or("This is the last chapter")?,
+        _ => return Err("No chapters available for this track".into()),
+    };
+    let (title, start) = (chapter.title.clone(), chapter.start);
+    drop(queue_lock);
+    seek_track(ctx, &lava, &queue, start).await?;
+    ctx.say(format!(
+        "Skipped to {} ({})",
+        title,
+        utils::length_to_string(start.as_secs())
+    ))
+    .await?;
 
     Ok(())
 }
@@ -753,7 +1196,7 @@ pub async fn repeat(
 ) -> Result<(), Error> {
     let (_, queue) = utils::voice_check(&ctx, false).await?;
     let mut queue_lock = queue.lock().await;
-    queue_lock.set_loop_mode(mode, ctx.author().id);
+    queue_lock.set_loop_mode(mode, ctx.author().id).await;
     ctx.say(format!("Loop mode set to {mode}")).await?;
 
     Ok(())
@@ -775,6 +1218,167 @@ pub async fn volume(
     Ok(())
 }
 
+#[poise::command(
+    slash_command,
+    category = "Music",
+    subcommands(
+        "filter_equalizer",
+        "filter_timescale",
+        "filter_rotation",
+        "filter_tremolo",
+        "filter_vibrato",
+        "filter_karaoke",
+        "filter_lowpass",
+        "filter_preset",
+        "filter_reset"
+    )
+)]
+pub async fn filter(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+#[poise::command(slash_command, rename = "equalizer", category = "Music")]
+pub async fn filter_equalizer(
+    ctx: Context<'_>,
+    #[description = "Band, from 0 (25 Hz) to 14 (16 kHz)"]
+    #[min = 0]
+    #[max = 14]
+    band: usize,
+    #[description = "Gain, from -0.25 to 1, 0 is unchanged"]
+    #[min = -0.25]
+    #[max = 1.0]
+    gain: f64,
+) -> Result<(), Error> {
+    update_filters(ctx, |filters| filters.set_band(band, gain)).await
+}
+
+#[poise::command(slash_command, rename = "timescale", category = "Music")]
+pub async fn filter_timescale(
+    ctx: Context<'_>,
+    #[description = "Playback speed, 1 is unchanged"]
+    #[min = 0.1]
+    #[max = 5.0]
+    speed: Option<f64>,
+    #[description = "Pitch, 1 is unchanged"]
+    #[min = 0.1]
+    #[max = 5.0]
+    pitch: Option<f64>,
+    #[description = "Rate (speed and pitch together), 1 is unchanged"]
+    #[min = 0.1]
+    #[max = 5.0]
+    rate: Option<f64>,
+) -> Result<(), Error> {
+    update_filters(ctx, |filters| {
+        let (current_speed, current_pitch, current_rate) = filters.timescale();
+        filters.set_timescale(
+            speed.unwrap_or(current_speed),
+            pitch.unwrap_or(current_pitch),
+            rate.unwrap_or(current_rate),
+        )
+    })
+    .await
+}
+
+#[poise::command(slash_command, rename = "rotation", category = "Music")]
+pub async fn filter_rotation(
+    ctx: Context<'_>,
+    #[description = "How many times per second the audio goes around, 0 disables"]
+    #[min = 0.0]
+    #[max = 5.0]
+    hz: f64,
+) -> Result<(), Error> {
+    update_filters(ctx, |filters| filters.set_rotation(hz)).await
+}
+
+#[poise::command(slash_command, rename = "tremolo", category = "Music")]
+pub async fn filter_tremolo(
+    ctx: Context<'_>,
+    #[description = "Depth, 0 disables"]
+    #[min = 0.0]
+    #[max = 1.0]
+    depth: f64,
+    #[description = "Frequency in Hz, 2 by default"]
+    #[min = 0.1]
+    #[max = 14.0]
+    frequency: Option<f64>,
+) -> Result<(), Error> {
+    update_filters(ctx, |filters| {
+        filters.set_tremolo(frequency.unwrap_or(2.0), depth)
+    })
+    .await
+}
+
+#[poise::command(slash_command, rename = "vibrato", category = "Music")]
+pub async fn filter_vibrato(
+    ctx: Context<'_>,
+    #[description = "Depth, 0 disables"]
+    #[min = 0.0]
+    #[max = 1.0]
+    depth: f64,
+    #[description = "Frequency in Hz, 2 by default"]
+    #[min = 0.1]
+    #[max = 14.0]
+    frequency: Option<f64>,
+) -> Result<(), Error> {
+    update_filters(ctx, |filters| {
+        filters.set_vibrato(frequency.unwrap_or(2.0), depth)
+    })
+    .await
+}
+
+#[poise::command(slash_command, rename = "karaoke", category = "Music")]
+pub async fn filter_karaoke(
+    ctx: Context<'_>,
+    #[description = "Remove the vocals"] enabled: bool,
+) -> Result<(), Error> {
+    update_filters(ctx, |filters| filters.set_karaoke(enabled)).await
+}
+
+#[poise::command(slash_command, rename = "lowpass", category = "Music")]
+pub async fn filter_lowpass(
+    ctx: Context<'_>,
+    #[description = "Smoothing, higher values cut more of the high frequencies, 1 disables"]
+    #[min = 1.0]
+    #[max = 100.0]
+    smoothing: f64,
+) -> Result<(), Error> {
+    update_filters(ctx, |filters| filters.set_low_pass(smoothing)).await
+}
+
+#[poise::command(slash_command, rename = "preset", category = "Music")]
+pub async fn filter_preset(
+    ctx: Context<'_>,
+    #[description = "Preset"] preset: Preset,
+) -> Result<(), Error> {
+    update_filters(ctx, |filters| filters.apply_preset(preset)).await
+}
+
+#[poise::command(slash_command, rename = "reset", category = "Music")]
+pub async fn filter_reset(ctx: Context<'_>) -> Result<(), Error> {
+    update_filters(ctx, |filters| *filters = AudioFilters::default()).await
+}
+
+async fn update_filters(
+    ctx: Context<'_>,
+    update: impl FnOnce(&mut AudioFilters),
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let (lava, queue) = utils::voice_check(&ctx, false).await?;
+    let mut queue_lock = queue.lock().await;
+    update(&mut queue_lock.filters);
+    lava.set_filters(guild_id, queue_lock.filters.to_lavalink())
+        .await?;
+
+    let description = queue_lock
+        .filters
+        .description()
+        .unwrap_or_else(|| "No filters active".to_string());
+    ctx.send(|m| m.embed(|e| e.title("Filters").description(description)))
+        .await?;
+
+    Ok(())
+}
+
 #[poise::command(slash_command, category = "Music")]
 pub async fn lyrics(ctx: Context<'_>, #[description = "Query"] query: String) -> Result<(), Error> {
     let genius = &ctx.data().genius;
@@ -10,4 +10,5 @@ pub struct Config {
     pub spotify_id: String,
     pub spotify_secret: String,
     pub genius_token: String,
+    pub sponsorblock_url: Option<String>,
 }
@@ -14,7 +14,7 @@ pub enum Error {
     Minecraft(MinecraftError),
     Sqlx(SqlxError),
     Songbird(SongbirdError),
-    Spotify(String),
+    Spotify(SpotifyError),
 
     Join(String),
     Command(String),
@@ -37,6 +37,27 @@ impl std::fmt::Display for Error {
     }
 }
 
+#[derive(Debug)]
+pub enum SpotifyError {
+    Client(SpotifyClientError),
+    InvalidId(SpotifyIdError),
+    InvalidLink(String),
+    UnsupportedLink(String),
+    Shortlink(String),
+}
+
+impl std::fmt::Display for SpotifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Client(err) => write!(f, "{err}"),
+            Self::InvalidId(err) => write!(f, "invalid id: {err}"),
+            Self::InvalidLink(link) => write!(f, "invalid link: {link}"),
+            Self::UnsupportedLink(kind) => write!(f, "{kind} links aren't supported"),
+            Self::Shortlink(link) => write!(f, "couldn't resolve the link {link}"),
+        }
+    }
+}
+
 impl From<SerenityError> for Error {
     fn from(err: SerenityError) -> Self {
         Self::Serenity(err)
@@ -72,13 +93,18 @@ impl From<SongbirdError> for Error {
         Self::Songbird(err)
     }
 }
+impl From<SpotifyError> for Error {
+    fn from(err: SpotifyError) -> Self {
+        Self::Spotify(err)
+    }
+}
 impl From<SpotifyClientError> for Error {
     fn from(err: SpotifyClientError) -> Self {
-        Self::Spotify(err.to_string())
+        Self::Spotify(SpotifyError::Client(err))
     }
 }
 impl From<SpotifyIdError> for Error {
     fn from(err: SpotifyIdError) -> Self {
-        Self::Spotify(err.to_string())
+        Self::Spotify(SpotifyError::InvalidId(err))
     }
 }
@@ -1,19 +1,32 @@
 use crate::{
     error::Error,
+    interactions,
+    music::{
+        autoplay, crossfade, history, inactivity, matching, player, queue::Queue, resolver,
+        sponsorblock::SponsorBlock, utils,
+    },
     shared_data::{Data, Guilds},
 };
 use lavalink_rs::{
     gateway::LavalinkEventHandler,
-    model::{Event, SendOpcode, TrackFinish, TrackStart, VoiceUpdate},
+    model::{Event, PlayerUpdate, SendOpcode, TrackFinish, TrackStart, VoiceUpdate},
     LavalinkClient,
 };
-use serenity::{async_trait, http::Http, model::id::GuildId, prelude::*};
-use std::sync::Arc;
+use rspotify::ClientCredsSpotify;
+use serenity::{
+    async_trait,
+    http::Http,
+    model::{application::interaction::Interaction, id::GuildId, voice::VoiceState},
+    prelude::*,
+};
+use std::{sync::Arc, time::Duration};
 use tracing::{error, info};
 
 pub struct LavalinkHandler {
     pub guilds: Guilds,
     pub http: Arc<Http>,
+    pub spotify: ClientCredsSpotify,
+    pub sponsorblock: Option<SponsorBlock>,
 }
 
 pub async fn event_listener(
@@ -22,61 +35,104 @@ pub async fn event_listener(
     _framework: &poise::FrameworkContext<'_, Data, Error>,
     data: &Data,
 ) -> Result<(), Error> {
-    if let poise::Event::VoiceStateUpdate { old, new } = event {
-        if new.user_id == ctx.cache.current_user_id() {
-            if let Some(old) = old {
-                if old.channel_id != new.channel_id {
-                    let guild_id = new.guild_id.unwrap();
-                    if new.channel_id.is_some() {
-                        info!("Moved channel in guild {guild_id}");
-
-                        let lava = &data.lavalink;
-                        if lava.pause(guild_id).await.is_err() {
-                            error!("Error pausing track");
-                        }
+    match event {
+        poise::Event::GuildCreate { guild, .. } => {
+            data.guilds.get(guild.id).await?;
+        }
+        poise::Event::GuildDelete { incomplete, .. } => {
+            // unavailable guilds are outages, not removals
+            if !incomplete.unavailable {
+                if let Some(guild) = data.guilds.remove(incomplete.id).await {
+                    info!("Removed from guild {}", incomplete.id);
+                    let queue = guild.lock().await.queue.clone();
+                    queue.lock().await.clean_up().await;
+                    let _err = data.lavalink.destroy(incomplete.id).await;
+                    if let Some(manager) = songbird::get(ctx).await {
+                        let _err = manager.remove(incomplete.id).await;
+                    }
+                }
+            }
+        }
+        poise::Event::VoiceStateUpdate { old, new } => {
+            voice_state_update(ctx, old, new, data).await?
+        }
+        poise::Event::InteractionCreate {
+            interaction: Interaction::MessageComponent(mci),
+        } => interactions::dispatch(ctx, mci, data).await?,
+        _ => {}
+    }
+    Ok(())
+}
 
-                        // wait for the call to update
-                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-                        let manager = songbird::get(ctx).await.unwrap();
-                        let call = manager.get(guild_id).unwrap();
-                        let call_lock = call.lock().await;
-                        let info = call_lock.current_connection().unwrap().clone();
-
-                        let event = Event {
-                            token: info.token,
-                            endpoint: info.endpoint,
-                            guild_id: info.guild_id.to_string(),
-                        };
-                        let payload = VoiceUpdate {
-                            session_id: info.session_id,
-                            event,
-                        };
+async fn voice_state_update(
+    ctx: &Context,
+    old: &Option<VoiceState>,
+    new: &VoiceState,
+    data: &Data,
+) -> Result<(), Error> {
+    if new.user_id != ctx.cache.current_user_id() {
+        if let Some(guild_id) = new.guild_id {
+            inactivity::check(ctx, &data.lavalink, &data.guilds, guild_id).await;
+        }
+        return Ok(());
+    }
 
-                        let socket;
-                        {
-                            let lava_inner = lava.inner.lock();
-                            socket = lava_inner.socket_sender.read().as_ref().unwrap().clone();
-                        }
+    if let Some(old) = old {
+        if old.channel_id != new.channel_id {
+            let guild_id = new.guild_id.unwrap();
+            if new.channel_id.is_some() {
+                info!("Moved channel in guild {guild_id}");
 
-                        if SendOpcode::VoiceUpdate(payload)
-                            .send(guild_id, socket)
-                            .await
-                            .is_err()
-                        {
-                            error!("Error updating voice channel!");
-                        }
+                let lava = &data.lavalink;
+                if lava.pause(guild_id).await.is_err() {
+                    error!("Error pausing track");
+                }
 
-                        if lava.resume(guild_id).await.is_err() {
-                            error!("Error resuming track");
-                        }
-                    } else {
-                        let queue = data.guilds.get_queue(guild_id).await;
-                        let mut queue_lock = queue.lock().await;
-                        queue_lock.clean_up();
-                        let lava = &data.lavalink;
-                        let _err = lava.destroy(guild_id).await;
-                    }
+                // wait for the call to update
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                let manager = songbird::get(ctx).await.unwrap();
+                let call = manager.get(guild_id).unwrap();
+                let call_lock = call.lock().await;
+                let info = call_lock.current_connection().unwrap().clone();
+
+                let event = Event {
+                    token: info.token,
+                    endpoint: info.endpoint,
+                    guild_id: info.guild_id.to_string(),
+                };
+                let payload = VoiceUpdate {
+                    session_id: info.session_id,
+                    event,
+                };
+
+                let socket;
+                {
+                    let lava_inner = lava.inner.lock();
+                    socket = lava_inner.socket_sender.read().as_ref().unwrap().clone();
+                }
+
+                if SendOpcode::VoiceUpdate(payload)
+                    .send(guild_id, socket)
+                    .await
+                    .is_err()
+                {
+                    error!("Error updating voice channel!");
                 }
+
+                if lava.resume(guild_id).await.is_err() {
+                    error!("Error resuming track");
+                }
+
+                let queue = data.guilds.get_queue(guild_id).await?;
+                let mut queue_lock = queue.lock().await;
+                queue_lock.voice_channel_id = new.channel_id;
+                queue_lock.save().await;
+            } else {
+                let queue = data.guilds.get_queue(guild_id).await?;
+                let mut queue_lock = queue.lock().await;
+                queue_lock.clean_up().await;
+                let lava = &data.lavalink;
+                let _err = lava.destroy(guild_id).await;
             }
         }
     }
@@ -85,16 +141,141 @@ pub async fn event_listener(
 
 #[async_trait]
 impl LavalinkEventHandler for LavalinkHandler {
-    async fn track_start(&self, _lava: LavalinkClient, event: TrackStart) {
+    async fn track_start(&self, lava: LavalinkClient, event: TrackStart) {
         info!("Track started in guild {}", event.guild_id);
+        let guild_id = GuildId(event.guild_id.0);
+        let guild = match self.guilds.get(guild_id).await {
+            Ok(guild) => guild,
+            Err(why) => {
+                error!("Error getting guild {}: {}", guild_id, why);
+                return;
+            }
+        };
+        let (queue, crossfade) = {
+            let guild_lock = guild.lock().await;
+            (guild_lock.queue.clone(), guild_lock.crossfade)
+        };
+        let mut queue_lock = queue.lock().await;
+
+        crossfade::restart(&lava, &queue, &mut queue_lock, guild_id, crossfade, None).await;
+
+        if let Some(track) = queue_lock.current_track.clone() {
+            match history::record(&self.guilds.database, guild_id, &track).await {
+                Ok(id) => queue_lock.history_id = Some(id),
+                Err(why) => error!("Error saving history in guild {}: {}", guild_id, why),
+            }
+        }
+        player::update(&self.http, &lava, guild_id, &mut queue_lock).await;
+        resolver::spawn(lava.clone(), self.guilds.database.clone(), queue.clone());
+
+        queue_lock.segments = None;
+        if let Some(sponsorblock) = &self.sponsorblock {
+            let video_id = match &queue_lock.current_track {
+                Some(track) if utils::is_youtube(&track.query) => track.identifier(),
+                _ => None,
+            };
+            if let Some(video_id) = video_id {
+                tokio::spawn(load_segments(
+                    sponsorblock.clone(),
+                    queue.clone(),
+                    video_id.to_string(),
+                ));
+            }
+        }
     }
     async fn track_finish(&self, lava: LavalinkClient, event: TrackFinish) {
         info!("Track finished in guild {}", event.guild_id);
         let guild_id = GuildId(event.guild_id.0);
-        let queue = self.guilds.get_queue(guild_id).await;
+        let guild = match self.guilds.get(guild_id).await {
+            Ok(guild) => guild,
+            Err(why) => {
+                error!("Error getting guild {}: {}", guild_id, why);
+                return;
+            }
+        };
+        let (queue, autoplay) = {
+            let guild_lock = guild.lock().await;
+            (guild_lock.queue.clone(), guild_lock.autoplay)
+        };
+        let mut queue_lock = queue.lock().await;
+
+        if let Some(id) = queue_lock.history_id.take() {
+            if queue_lock.skipped() {
+                if let Err(why) = history::mark_skipped(&self.guilds.database, id).await {
+                    error!("Error saving history in guild {}: {}", guild_id, why);
+                }
+            }
+        }
+        if event.reason == "LOAD_FAILED" {
+            let spotify_id = queue_lock
+                .current_track
+                .as_ref()
+                .and_then(|track| track.spotify.as_ref())
+                .map(|spotify| spotify.id.clone());
+            if let Some(spotify_id) = spotify_id {
+                if let Err(why) = matching::forget(&self.guilds.database, &spotify_id).await {
+                    error!("Error removing match for {}: {}", spotify_id, why);
+                }
+            }
+        }
+        let stopped = queue_lock.take_stopped();
+        queue_lock.play_next(lava.clone()).await;
+        if queue_lock.current_track.is_none() && autoplay && !stopped {
+            drop(queue_lock);
+            autoplay::play_related(&self.spotify, &lava, &self.guilds.database, &queue).await;
+            queue_lock = queue.lock().await;
+        }
+        if queue_lock.current_track.is_none() {
+            if queue_lock.filters.fade() < 1.0 {
+                crossfade::set_level(&lava, guild_id, &mut queue_lock, 1.0).await;
+            }
+            player::update(&self.http, &lava, guild_id, &mut queue_lock).await;
+        }
+    }
+    async fn player_update(&self, lava: LavalinkClient, event: PlayerUpdate) {
+        let guild_id = GuildId(event.guild_id.0);
+        let queue = match self.guilds.get_queue(guild_id).await {
+            Ok(queue) => queue,
+            Err(why) => {
+                error!("Error getting queue in guild {}: {}", guild_id, why);
+                return;
+            }
+        };
         let mut queue_lock = queue.lock().await;
 
-        queue_lock.play_next(lava, &self.http).await;
+        let mut position = Duration::from_millis(event.state.position as u64);
+        let segment_end = queue_lock
+            .segments
+            .as_mut()
+            .and_then(|segments| segments.skip_from(position));
+        if let Some(end) = segment_end {
+            info!("Skipping segment in guild {}", guild_id);
+            match lava.seek(guild_id, end).await {
+                Ok(_) => position = end,
+                Err(why) => error!("Error skipping segment in guild {}: {}", guild_id, why),
+            }
+        }
+        queue_lock.save_progress(position).await;
+        player::progress(&self.http, &lava, guild_id, &mut queue_lock).await;
+    }
+}
+
+async fn load_segments(sponsorblock: SponsorBlock, queue: Arc<Mutex<Queue>>, video_id: String) {
+    let segments = match sponsorblock.segments(&video_id).await {
+        Ok(segments) => segments,
+        Err(why) => {
+            error!("Error getting segments for {}: {}", video_id, why);
+            return;
+        }
+    };
+    let mut queue_lock = queue.lock().await;
+    // the track could have changed in the meantime
+    let current = queue_lock
+        .current_track
+        .as_ref()
+        .and_then(|track| track.identifier());
+    if current == Some(video_id.as_str()) {
+        queue_lock.segments = Some(segments);
     }
 }
 
@@ -1,4 +1,27 @@
-use crate::Context;
+use crate::{error::Error, music::utils::Caller, Context};
+
+const DJ_COMMANDS: [&str; 20] = [
+    "stop",
+    "leave",
+    "clear",
+    "skip",
+    "remove",
+    "move",
+    "swap",
+    "volume",
+    "seek",
+    "forward",
+    "rewind",
+    "nextchapter",
+    "loop",
+    "shuffle",
+    "previous",
+    "replay",
+    "skipto",
+    "playnext",
+    "playnow",
+    "filter",
+];
 
 pub fn before(ctx: Context) {
     let guild_name = match ctx.guild() {
@@ -12,3 +35,53 @@ pub fn before(ctx: Context) {
         guild_name
     );
 }
+
+pub async fn music_check(ctx: Context<'_>) -> Result<bool, Error> {
+    let caller = match Caller::from_command(&ctx) {
+        Some(caller) => caller,
+        None => return Ok(true),
+    };
+    if ctx.command().category == Some("Music") && !text_channel_check(&caller).await? {
+        return Ok(false);
+    }
+    // subcommands are checked as their command group
+    let qualified_name = &ctx.command().qualified_name;
+    let name = qualified_name.split(' ').next().unwrap_or(qualified_name);
+    dj_check(&caller, name).await
+}
+
+pub async fn text_channel_check(caller: &Caller<'_>) -> Result<bool, Error> {
+    let guild = caller.data.guilds.get(caller.guild_id).await?;
+    let text_channel_id = guild.lock().await.text_channel_id;
+    match text_channel_id {
+        Some(text_channel_id) if text_channel_id != caller.channel_id => Err(Error::Command(
+            format!("Music commands can only be used in <#{text_channel_id}>"),
+        )),
+        _ => Ok(true),
+    }
+}
+
+pub async fn dj_check(caller: &Caller<'_>, name: &str) -> Result<bool, Error> {
+    if !DJ_COMMANDS.contains(&name) || caller.is_dj().await? {
+        return Ok(true);
+    }
+
+    let guilds = &caller.data.guilds;
+    let vote_skip = guilds.get(caller.guild_id).await?.lock().await.vote_skip;
+    let queue = guilds.get_queue(caller.guild_id).await?;
+    let queue_lock = queue.lock().await;
+    let is_requester = queue_lock
+        .current_track
+        .as_ref()
+        .map_or(false, |track| track.requester == caller.user_id);
+    match name {
+        // with round robin these only touch the user's own queue
+        "clear" | "shuffle" | "loop" | "playnext" if queue_lock.round_robin() => Ok(true),
+        // the requester of each track is checked by the commands themselves
+        "remove" | "move" | "swap" => Ok(true),
+        "skip" | "seek" | "replay" if is_requester => Ok(true),
+        // non-DJs add a vote instead
+        "skip" if vote_skip => Ok(true),
+        _ => Err("You need a DJ role to use this command".into()),
+    }
+}
@@ -1,33 +1,92 @@
-use crate::music::queue::Queue;
-use serenity::{model::id::GuildId, prelude::Mutex};
+use crate::{
+    error::Error,
+    music::queue::{DuplicatePolicy, Queue, QueueLimits},
+};
+use serenity::{
+    model::id::{ChannelId, GuildId, RoleId},
+    prelude::Mutex,
+};
 use sqlx::PgPool;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 pub struct Guild {
     pub guild_id: GuildId,
     pub queue: Arc<Mutex<Queue>>,
     pub prefix: String,
+    pub dj_role_ids: Vec<RoleId>,
+    pub vote_skip: bool,
+    pub vote_skip_threshold: u8,
+    pub text_channel_id: Option<ChannelId>,
+    pub voice_channel_id: Option<ChannelId>,
+    pub alone_timeout: Duration,
+    pub idle_timeout: Duration,
+    pub limits: QueueLimits,
+    pub duplicate_policy: DuplicatePolicy,
+    pub queue_page_size: usize,
+    pub autoplay: bool,
+    pub crossfade: Duration,
 }
 impl Guild {
-    pub async fn new(guild_id: GuildId, database: &PgPool) -> Arc<Mutex<Self>> {
-        let (prefix, round_robin) = match sqlx::query!(
-            "SELECT prefix, round_robin FROM guilds WHERE guild_id = $1",
+    pub async fn new(guild_id: GuildId, database: &PgPool) -> Result<Arc<Mutex<Self>>, Error> {
+        let settings = sqlx::query!(
+            "SELECT prefix, round_robin, dj_role_ids, vote_skip, vote_skip_threshold,
+                text_channel_id, voice_channel_id, alone_timeout, idle_timeout,
+                max_queue_size, max_user_tracks, max_track_length, max_playlist_size,
+                duplicate_policy, queue_page_size, autoplay, crossfade
+            FROM guilds WHERE guild_id = $1",
             guild_id.0 as i64
         )
-        .fetch_one(database)
-        .await
-        {
-            Ok(result) => (
-                result.prefix.unwrap_or_else(|| "!".to_string()),
-                result.round_robin,
-            ),
-            Err(_) => ("!".to_string(), false),
-        };
+        .fetch_optional(database)
+        .await?;
+        let round_robin = settings
+            .as_ref()
+            .map_or(false, |settings| settings.round_robin);
 
-        Arc::new(Mutex::new(Guild {
+        let mut guild = Guild {
             guild_id,
-            queue: Queue::new(guild_id, None, round_robin),
-            prefix,
-        }))
+            queue: Queue::load(guild_id, round_robin, database.clone()).await,
+            prefix: "!".to_string(),
+            dj_role_ids: Vec::new(),
+            vote_skip: false,
+            vote_skip_threshold: 50,
+            text_channel_id: None,
+            voice_channel_id: None,
+            alone_timeout: Duration::from_secs(300),
+            idle_timeout: Duration::from_secs(300),
+            limits: QueueLimits::default(),
+            duplicate_policy: DuplicatePolicy::Allow,
+            queue_page_size: 20,
+            autoplay: false,
+            crossfade: Duration::ZERO,
+        };
+        if let Some(settings) = settings {
+            if let Some(prefix) = settings.prefix {
+                guild.prefix = prefix;
+            }
+            guild.dj_role_ids = settings
+                .dj_role_ids
+                .unwrap_or_default()
+                .into_iter()
+                .map(|id| RoleId(id as u64))
+                .collect();
+            guild.vote_skip = settings.vote_skip;
+            guild.vote_skip_threshold = settings.vote_skip_threshold as u8;
+            guild.text_channel_id = settings.text_channel_id.map(|id| ChannelId(id as u64));
+            guild.voice_channel_id = settings.voice_channel_id.map(|id| ChannelId(id as u64));
+            guild.alone_timeout = Duration::from_secs(settings.alone_timeout as u64);
+            guild.idle_timeout = Duration::from_secs(settings.idle_timeout as u64);
+            guild.limits = QueueLimits {
+                max_queue_size: settings.max_queue_size as usize,
+                max_user_tracks: settings.max_user_tracks as usize,
+                max_track_length: Duration::from_secs(settings.max_track_length as u64),
+                max_playlist_size: settings.max_playlist_size as usize,
+            };
+            guild.duplicate_policy = settings.duplicate_policy.into();
+            guild.queue_page_size = settings.queue_page_size as usize;
+            guild.autoplay = settings.autoplay;
+            guild.crossfade = Duration::from_secs(settings.crossfade as u64);
+        }
+
+        Ok(Arc::new(Mutex::new(guild)))
     }
 }
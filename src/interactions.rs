@@ -0,0 +1,567 @@
+use crate::{
+    commands::music::{favourites_embed, history_embed, queue_embed},
+    error::Error,
+    framework_functions,
+    music::{
+        favourites,
+        player::{self, Action},
+        queue::QueuedTrack,
+        utils::Caller,
+    },
+    shared_data::Data,
+};
+use serenity::{
+    builder::{CreateComponents, CreateEmbed},
+    model::{
+        application::{
+            component::ButtonStyle,
+            interaction::{
+                message_component::MessageComponentInteraction, InteractionResponseType,
+            },
+        },
+        id::{GuildId, UserId},
+    },
+    prelude::Context,
+};
+use std::fmt;
+
+pub enum ComponentId {
+    Player(GuildId, Action),
+    Like(GuildId),
+    Queue(GuildId, PageButton),
+    QueueSelect(GuildId, QueueAction, usize),
+    UserQueue(GuildId, UserId, PageButton),
+    History(GuildId, PageButton),
+    Favourites(GuildId, UserId, PageButton),
+    // the video identifier, a full uri could go over the 100 character limit of custom ids
+    Search(GuildId, UserId, String),
+}
+impl ComponentId {
+    pub fn parse(custom_id: &str) -> Option<Self> {
+        let mut parts = custom_id.splitn(4, ':');
+        let action = parts.next()?;
+        let guild_id = GuildId(parts.next()?.parse().ok()?);
+        let id = match action {
+            "player" => Self::Player(guild_id, Action::from_name(parts.next()?)?),
+            "like" => Self::Like(guild_id),
+            "queue" => Self::Queue(guild_id, PageButton::parse(parts.next()?)?),
+            "queueselect" => Self::QueueSelect(
+                guild_id,
+                QueueAction::from_name(parts.next()?)?,
+                parts.next()?.parse().ok()?,
+            ),
+            "myqueue" => Self::UserQueue(
+                guild_id,
+                UserId(parts.next()?.parse().ok()?),
+                PageButton::parse(parts.next()?)?,
+            ),
+            "history" => Self::History(guild_id, PageButton::parse(parts.next()?)?),
+            "favourites" => Self::Favourites(
+                guild_id,
+                UserId(parts.next()?.parse().ok()?),
+                PageButton::parse(parts.next()?)?,
+            ),
+            "search" => Self::Search(
+                guild_id,
+                UserId(parts.next()?.parse().ok()?),
+                parts.next()?.to_string(),
+            ),
+            _ => return None,
+        };
+        Some(id)
+    }
+}
+impl fmt::Display for ComponentId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Player(guild_id, action) => write!(f, "player:{}:{}", guild_id.0, action.name()),
+            Self::Like(guild_id) => write!(f, "like:{}", guild_id.0),
+            Self::Queue(guild_id, page) => write!(f, "queue:{}:{}", guild_id.0, page),
+            Self::QueueSelect(guild_id, action, page) => {
+                write!(f, "queueselect:{}:{}:{}", guild_id.0, action.name(), page)
+            }
+            Self::UserQueue(guild_id, user_id, page) => {
+                write!(f, "myqueue:{}:{}:{}", guild_id.0, user_id.0, page)
+            }
+            Self::History(guild_id, page) => write!(f, "history:{}:{}", guild_id.0, page),
+            Self::Favourites(guild_id, user_id, page) => {
+                write!(f, "favourites:{}:{}:{}", guild_id.0, user_id.0, page)
+            }
+            Self::Search(guild_id, user_id, identifier) => {
+                write!(f, "search:{}:{}:{}", guild_id.0, user_id.0, identifier)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum PageButton {
+    First,
+    Previous(usize),
+    Next(usize),
+    Last,
+}
+impl PageButton {
+    fn parse(value: &str) -> Option<Self> {
+        let button = match value.split_once('-') {
+            Some(("prev", page)) => Self::Previous(page.parse().ok()?),
+            Some(("next", page)) => Self::Next(page.parse().ok()?),
+            None if value == "first" => Self::First,
+            None if value == "last" => Self::Last,
+            _ => return None,
+        };
+        Some(button)
+    }
+
+    // pages past the end are clamped to the last page
+    pub fn page(self) -> usize {
+        match self {
+            Self::First => 0,
+            Self::Previous(page) => page.saturating_sub(1),
+            Self::Next(page) => page + 1,
+            Self::Last => usize::MAX,
+        }
+    }
+}
+impl fmt::Display for PageButton {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::First => write!(f, "first"),
+            Self::Previous(page) => write!(f, "prev-{}", page),
+            Self::Next(page) => write!(f, "next-{}", page),
+            Self::Last => write!(f, "last"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum QueueAction {
+    Jump,
+    Remove,
+    Top,
+}
+impl QueueAction {
+    const ALL: [QueueAction; 3] = [QueueAction::Jump, QueueAction::Remove, QueueAction::Top];
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            QueueAction::Jump => "jump",
+            QueueAction::Remove => "remove",
+            QueueAction::Top => "top",
+        }
+    }
+
+    // the slash command whose checks apply to the menu
+    fn command(self) -> &'static str {
+        match self {
+            QueueAction::Jump => "skipto",
+            QueueAction::Remove => "remove",
+            QueueAction::Top => "move",
+        }
+    }
+
+    fn placeholder(self) -> &'static str {
+        match self {
+            QueueAction::Jump => "Jump to a track",
+            QueueAction::Remove => "Remove a track",
+            QueueAction::Top => "Move a track to the top",
+        }
+    }
+}
+
+pub fn pagination(
+    c: &mut CreateComponents,
+    page: usize,
+    page_count: usize,
+    id: impl Fn(PageButton) -> ComponentId,
+) -> &mut CreateComponents {
+    if page_count > 1 {
+        let first = page == 0;
+        let last = page + 1 >= page_count;
+        c.create_action_row(|r| {
+            for (label, button, disabled) in [
+                ("⏮", PageButton::First, first),
+                ("<", PageButton::Previous(page), first),
+                (">", PageButton::Next(page), last),
+                ("⏭", PageButton::Last, last),
+            ] {
+                r.create_button(|b| {
+                    b.style(ButtonStyle::Primary)
+                        .label(label)
+                        .custom_id(id(button))
+                        .disabled(disabled)
+                });
+            }
+            r
+        });
+    }
+    c
+}
+
+pub fn queue_components<'a>(
+    c: &'a mut CreateComponents,
+    guild_id: GuildId,
+    page: usize,
+    page_count: usize,
+    tracks: &[(usize, u64, String)],
+) -> &'a mut CreateComponents {
+    pagination(c, page, page_count, |button| {
+        ComponentId::Queue(guild_id, button)
+    });
+    if tracks.is_empty() {
+        return c;
+    }
+    for action in QueueAction::ALL {
+        c.create_action_row(|r| {
+            r.create_select_menu(|m| {
+                m.custom_id(ComponentId::QueueSelect(guild_id, action, page))
+                    .placeholder(action.placeholder())
+                    .options(|o| {
+                        for (index, track_id, title) in tracks {
+                            // option labels are limited to 100 characters
+                            let label: String = format!("{}. {}", index + 1, title)
+                                .chars()
+                                .take(100)
+                                .collect();
+                            // the track id shows whether the track is still at that position
+                            o.create_option(|o| {
+                                o.label(label).value(format!("{index}:{track_id}"))
+                            });
+                        }
+                        o
+                    })
+            })
+        });
+    }
+    c
+}
+
+pub async fn dispatch(
+    ctx: &Context,
+    mci: &MessageComponentInteraction,
+    data: &Data,
+) -> Result<(), Error> {
+    let id = match ComponentId::parse(&mci.data.custom_id) {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    let result = match id {
+        ComponentId::Player(guild_id, action) => {
+            player::handle_button(ctx, mci, data, guild_id, action).await
+        }
+        ComponentId::Like(guild_id) => like(ctx, mci, data, guild_id).await,
+        ComponentId::Queue(guild_id, page) => {
+            update_queue(ctx, mci, data, guild_id, page.page()).await
+        }
+        ComponentId::QueueSelect(guild_id, action, page) => {
+            queue_select(ctx, mci, data, guild_id, action, page).await
+        }
+        ComponentId::UserQueue(guild_id, user_id, page) => {
+            let (embed, page, page_count, _) =
+                queue_embed(data, guild_id, Some(user_id), page.page()).await?;
+            update_page(ctx, mci, embed, page, page_count, |page| {
+                ComponentId::UserQueue(guild_id, user_id, page)
+            })
+            .await
+        }
+        ComponentId::History(guild_id, page) => {
+            let (embed, page, page_count) =
+                history_embed(&data.database, guild_id, page.page()).await?;
+            update_page(ctx, mci, embed, page, page_count, |page| {
+                ComponentId::History(guild_id, page)
+            })
+            .await
+        }
+        ComponentId::Favourites(guild_id, user_id, page) => {
+            let (embed, page, page_count) =
+                favourites_embed(&data.database, user_id, page.page()).await?;
+            update_page(ctx, mci, embed, page, page_count, |page| {
+                ComponentId::Favourites(guild_id, user_id, page)
+            })
+            .await
+        }
+        ComponentId::Search(_, user_id, identifier) => {
+            search(ctx, mci, data, user_id, &identifier).await
+        }
+    };
+
+    if let Err(why) = result {
+        respond(ctx, mci, why.to_string()).await?;
+    }
+    Ok(())
+}
+
+pub async fn respond(
+    ctx: &Context,
+    mci: &MessageComponentInteraction,
+    content: String,
+) -> Result<(), Error> {
+    mci.create_interaction_response(&ctx.http, |r| {
+        r.kind(InteractionResponseType::ChannelMessageWithSource)
+            .interaction_response_data(|d| d.content(content).ephemeral(true))
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn update_page(
+    ctx: &Context,
+    mci: &MessageComponentInteraction,
+    embed: CreateEmbed,
+    page: usize,
+    page_count: usize,
+    id: impl Fn(PageButton) -> ComponentId,
+) -> Result<(), Error> {
+    mci.create_interaction_response(&ctx.http, |r| {
+        r.kind(InteractionResponseType::UpdateMessage)
+            .interaction_response_data(|d| {
+                d.set_embeds([embed])
+                    .components(|c| pagination(c, page, page_count, id))
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn update_queue(
+    ctx: &Context,
+    mci: &MessageComponentInteraction,
+    data: &Data,
+    guild_id: GuildId,
+    page: usize,
+) -> Result<(), Error> {
+    let (embed, page, page_count, tracks) = queue_embed(data, guild_id, None, page).await?;
+    mci.create_interaction_response(&ctx.http, |r| {
+        r.kind(InteractionResponseType::UpdateMessage)
+            .interaction_response_data(|d| {
+                d.set_embeds([embed])
+                    .components(|c| queue_components(c, guild_id, page, page_count, &tracks))
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn queue_select(
+    ctx: &Context,
+    mci: &MessageComponentInteraction,
+    data: &Data,
+    guild_id: GuildId,
+    action: QueueAction,
+    page: usize,
+) -> Result<(), Error> {
+    let caller = match Caller::from_component(ctx, data, mci) {
+        Some(caller) if caller.guild_id == guild_id => caller,
+        _ => return Ok(()),
+    };
+    let (index, track_id): (usize, u64) = match mci
+        .data
+        .values
+        .first()
+        .and_then(|value| value.split_once(':'))
+    {
+        Some((index, track_id)) => match (index.parse(), track_id.parse()) {
+            (Ok(index), Ok(track_id)) => (index, track_id),
+            _ => return Ok(()),
+        },
+        None => return Ok(()),
+    };
+    framework_functions::text_channel_check(&caller).await?;
+    framework_functions::dj_check(&caller, action.command()).await?;
+
+    // the queue may have changed since the menu was sent
+    let changed = if action == QueueAction::Jump {
+        let (lava, queue) = caller.voice_check().await?;
+        let mut queue_lock = queue.lock().await;
+        if queue_lock.current_track.is_none() {
+            return Err("Nothing is playing".into());
+        }
+        let changed = queue_lock
+            .track_at(index)
+            .map_or(true, |track| track.id != track_id);
+        if !changed && queue_lock.skip_to(index, lava).await?.is_none() {
+            return Err("Index out of range".into());
+        }
+        changed
+    } else {
+        let is_dj = caller.is_dj().await?;
+        let queue = data.guilds.get_queue(guild_id).await?;
+        let mut queue_lock = queue.lock().await;
+        let changed = match queue_lock.track_at(index) {
+            Some(track) if track.id == track_id => {
+                if !is_dj && track.requester != caller.user_id {
+                    return Err("You can only change tracks you requested".into());
+                }
+                false
+            }
+            _ => true,
+        };
+        if !changed {
+            if action == QueueAction::Remove {
+                queue_lock.remove_at(index).await;
+            } else {
+                queue_lock.move_to_top(index).await;
+            }
+        }
+        changed
+    };
+
+    update_queue(ctx, mci, data, guild_id, page).await?;
+    if changed {
+        mci.create_followup_message(&ctx.http, |f| {
+            f.content("The queue has changed since this page was shown, pick the track again")
+                .ephemeral(true)
+        })
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn like(
+    ctx: &Context,
+    mci: &MessageComponentInteraction,
+    data: &Data,
+    guild_id: GuildId,
+) -> Result<(), Error> {
+    let queue = data.guilds.get_queue(guild_id).await?;
+    let track = {
+        let queue_lock = queue.lock().await;
+        if queue_lock.player.is_message(mci.message.id) {
+            queue_lock.current_track.clone()
+        } else {
+            None
+        }
+    };
+
+    let content = match track {
+        Some(track) => {
+            if favourites::add(&data.database, mci.user.id, &track).await? {
+                format!("Added {} to your favourites", track.title)
+            } else {
+                format!("{} is already in your favourites", track.title)
+            }
+        }
+        None => "This track is no longer playing".to_string(),
+    };
+    respond(ctx, mci, content).await
+}
+
+async fn search(
+    ctx: &Context,
+    mci: &MessageComponentInteraction,
+    data: &Data,
+    user_id: UserId,
+    identifier: &str,
+) -> Result<(), Error> {
+    if mci.user.id != user_id {
+        return Err("Only the user who searched can pick a result".into());
+    }
+    let caller = match Caller::from_component(ctx, data, mci) {
+        Some(caller) => caller,
+        None => return Ok(()),
+    };
+    framework_functions::text_channel_check(&caller).await?;
+    let (lava, queue) = caller.voice_check().await?;
+
+    // search results are always youtube videos
+    let uri = format!("https://www.youtube.com/watch?v={identifier}");
+    let mut query_result = lava.auto_search_tracks(uri).await?;
+    if query_result.tracks.is_empty() {
+        return Err("No matching videos found".into());
+    }
+    let track = QueuedTrack::new_initialized(query_result.tracks.remove(0), user_id);
+    let title = track.title.clone();
+    let duplicate = caller.enqueue(&queue, track, lava).await?;
+
+    let mut content = format!("{} added to queue.", title);
+    if duplicate {
+        content += "\nThis track was already in the queue";
+    }
+    mci.create_interaction_response(&ctx.http, |r| {
+        r.kind(InteractionResponseType::UpdateMessage)
+            .interaction_response_data(|d| {
+                d.content(content)
+                    .components(|c| c.set_action_rows(Vec::default()))
+                    .set_embeds([])
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn component_ids_round_trip() {
+        for custom_id in [
+            "player:1:volume_down",
+            "like:1",
+            "queue:1:first",
+            "queue:1:prev-3",
+            "queueselect:1:remove:2",
+            "myqueue:1:2:next-0",
+            "history:1:last",
+            "favourites:1:2:next-4",
+            "search:1:2:dQw4w9WgXcQ",
+        ] {
+            let parsed = ComponentId::parse(custom_id).expect(custom_id);
+            assert_eq!(parsed.to_string(), custom_id);
+        }
+    }
+
+    #[test]
+    fn invalid_component_ids() {
+        for custom_id in [
+            "",
+            "queue",
+            "queue:abc:first",
+            "queue:1:middle",
+            "queue:1:next-",
+            "player:1:rewind",
+            "unknown:1",
+        ] {
+            assert!(ComponentId::parse(custom_id).is_none(), "{custom_id}");
+        }
+    }
+
+    #[test]
+    fn custom_id_lengths() {
+        let longest = [
+            ComponentId::Search(
+                GuildId(u64::MAX),
+                UserId(u64::MAX),
+                "dQw4w9WgXcQ".to_string(),
+            ),
+            ComponentId::Favourites(
+                GuildId(u64::MAX),
+                UserId(u64::MAX),
+                PageButton::Next(usize::MAX),
+            ),
+            ComponentId::QueueSelect(GuildId(u64::MAX), QueueAction::Remove, usize::MAX),
+        ];
+        for id in longest {
+            assert!(id.to_string().len() <= 100, "{id}");
+        }
+    }
+
+    #[test]
+    fn page_buttons() {
+        assert_eq!(PageButton::parse("prev-0").unwrap().page(), 0);
+        assert_eq!(PageButton::parse("prev-3").unwrap().page(), 2);
+        assert_eq!(PageButton::parse("next-3").unwrap().page(), 4);
+        assert_eq!(PageButton::parse("last").unwrap().page(), usize::MAX);
+        assert_eq!(PageButton::Next(7).to_string(), "next-7");
+    }
+}
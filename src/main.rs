@@ -4,6 +4,7 @@ mod error;
 mod events;
 mod framework_functions;
 mod guild;
+mod interactions;
 mod music;
 mod shared_data;
 
@@ -45,32 +46,56 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             register(),
             commands::admin::roundrobin(),
             commands::admin::minecraftchannel(),
+            commands::admin::djrole(),
+            commands::admin::voteskip(),
+            commands::admin::musicchannel(),
+            commands::admin::inactivity(),
+            commands::admin::limits(),
+            commands::admin::duplicates(),
+            commands::admin::pagesize(),
+            commands::admin::autoplay(),
+            commands::admin::crossfade(),
             commands::general::ping(),
             commands::general::minecraft(),
             commands::general::vps(),
             commands::music::join(),
             commands::music::leave(),
             commands::music::play(),
+            commands::music::playnext(),
+            commands::music::playnow(),
             commands::music::playlist(),
             commands::music::search(),
             commands::music::nowplaying(),
             commands::music::queue(),
             commands::music::myqueue(),
+            commands::music::history(),
+            commands::music::saved(),
+            commands::music::like(),
+            commands::music::favourites(),
             commands::music::clear(),
             commands::music::stop(),
             commands::music::remove(),
             commands::music::mv(),
             commands::music::swap(),
             commands::music::skip(),
+            commands::music::skipto(),
+            commands::music::previous(),
+            commands::music::replay(),
             commands::music::shuffle(),
             commands::music::seek(),
+            commands::music::forward(),
+            commands::music::rewind(),
+            commands::music::chapters(),
+            commands::music::nextchapter(),
             commands::music::pause(),
             commands::music::resume(),
             commands::music::repeat(),
             commands::music::volume(),
+            commands::music::filter(),
             commands::music::lyrics(),
         ],
         pre_command: |ctx| Box::pin(async move { before(ctx) }),
+        command_check: Some(|ctx| Box::pin(async move { music_check(ctx).await })),
         event_handler: |ctx, event, framework, data| {
             Box::pin(async move { events::event_listener(ctx, event, &framework, data).await })
         },
@@ -0,0 +1,141 @@
+use crate::music::{
+    queue::{Queue, QueuedTrack},
+    spotify,
+};
+use lavalink_rs::LavalinkClient;
+use rspotify::{
+    clients::BaseClient,
+    model::{ArtistId, RecommendationsAttribute, SearchResult, SearchType},
+    ClientCredsSpotify, ClientResult,
+};
+use serenity::prelude::Mutex;
+use sqlx::PgPool;
+use tracing::error;
+
+const RECOMMENDATIONS: u32 = 20;
+const MAX_ATTEMPTS: usize = 5;
+
+// the queue is only locked to enqueue, looking up related tracks can take a while
+pub async fn play_related(
+    spotify: &ClientCredsSpotify,
+    lava: &LavalinkClient,
+    database: &PgPool,
+    queue: &Mutex<Queue>,
+) {
+    let recent: Vec<QueuedTrack> = queue
+        .lock()
+        .await
+        .previous_tracks()
+        .iter()
+        .cloned()
+        .collect();
+    let seed = match recent.last() {
+        Some(seed) => seed,
+        None => return,
+    };
+
+    let candidates = related_tracks(spotify, lava, seed)
+        .await
+        .into_iter()
+        .filter(|track| !is_recent(track, &recent));
+    for mut track in candidates.take(MAX_ATTEMPTS) {
+        if let Err(why) = track.init(lava, database).await {
+            error!("Error playing autoplay track: {}", why);
+            continue;
+        }
+        let mut queue_lock = queue.lock().await;
+        // something may have been queued in the meantime
+        if queue_lock.current_track.is_some() {
+            return;
+        }
+        match queue_lock.enqueue(track, lava.clone()).await {
+            Ok(_) => return,
+            Err(why) => error!("Error playing autoplay track: {}", why),
+        }
+    }
+}
+
+async fn related_tracks(
+    spotify: &ClientCredsSpotify,
+    lava: &LavalinkClient,
+    seed: &QueuedTrack,
+) -> Vec<QueuedTrack> {
+    match recommendations(spotify, seed).await {
+        Ok(tracks) if !tracks.is_empty() => return tracks,
+        Ok(_) => {}
+        Err(why) => error!("Error getting recommendations: {}", why),
+    }
+
+    // fall back to other tracks of the same artist
+    match lava.search_tracks(&seed.artist).await {
+        Ok(result) => result
+            .tracks
+            .into_iter()
+            .map(|track| QueuedTrack::new_initialized(track, seed.requester))
+            .collect(),
+        Err(why) => {
+            error!("Error searching for autoplay tracks: {}", why);
+            Vec::new()
+        }
+    }
+}
+
+async fn recommendations(
+    spotify: &ClientCredsSpotify,
+    seed: &QueuedTrack,
+) -> ClientResult<Vec<QueuedTrack>> {
+    let title = clean_title(&seed.title);
+    let query = if title.contains(" - ") {
+        title
+    } else {
+        format!("{} {}", seed.artist, title)
+    };
+    let track_id = match spotify
+        .search(&query, &SearchType::Track, None, None, Some(1), None)
+        .await?
+    {
+        SearchResult::Tracks(page) => page.items.into_iter().find_map(|track| track.id),
+        _ => None,
+    };
+    let track_id = match track_id {
+        Some(track_id) => track_id,
+        None => return Ok(Vec::new()),
+    };
+
+    let recommendations = spotify
+        .recommendations(
+            std::iter::empty::<RecommendationsAttribute>(),
+            None::<Vec<&ArtistId>>,
+            None::<Vec<&str>>,
+            Some([&track_id]),
+            None,
+            Some(RECOMMENDATIONS),
+        )
+        .await?;
+    spotify::from_simplified_tracks(spotify, &recommendations.tracks, seed.requester).await
+}
+
+fn is_recent(track: &QueuedTrack, recent: &[QueuedTrack]) -> bool {
+    let title = clean_title(&track.title).to_lowercase();
+    recent.iter().any(|recent| {
+        let recent_title = clean_title(&recent.title).to_lowercase();
+        recent.query == track.query
+            || recent_title.contains(&title)
+            || title.contains(&recent_title)
+    })
+}
+
+// drops the "(Official Video)" and "[Lyrics]" parts of video titles
+fn clean_title(title: &str) -> String {
+    let mut cleaned = String::new();
+    let mut depth = 0;
+    for c in title.chars() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' if depth > 0 => depth -= 1,
+            _ if depth == 0 => cleaned.push(c),
+            _ => {}
+        }
+    }
+    cleaned.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
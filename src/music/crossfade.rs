@@ -0,0 +1,158 @@
+use crate::music::queue::Queue;
+use lavalink_rs::LavalinkClient;
+use serenity::{model::id::GuildId, prelude::Mutex};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{task::JoinHandle, time::sleep};
+use tracing::error;
+
+const STEPS: u32 = 20;
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// how long before the fade out the next track is looked up
+const PREPARE_AHEAD: Duration = Duration::from_secs(10);
+
+// lavalink only reports the position every few seconds, so it's extrapolated in between
+#[derive(Clone, Copy)]
+struct Estimate {
+    reported: Duration,
+    // where the track was when that was reported, differs right after a seek
+    position: Duration,
+    at: Instant,
+}
+
+// (re)starts the fades of the current track, seeked_to is the position of a seek that
+// lavalink may not have reported yet
+pub async fn restart(
+    lava: &LavalinkClient,
+    queue: &Arc<Mutex<Queue>>,
+    queue_lock: &mut Queue,
+    guild_id: GuildId,
+    fade: Duration,
+    seeked_to: Option<Duration>,
+) {
+    if let Some(task) = queue_lock.fade_task.take() {
+        task.abort();
+    }
+    if fade.is_zero() {
+        if queue_lock.filters.fade() < 1.0 {
+            set_level(lava, guild_id, queue_lock, 1.0).await;
+        }
+        return;
+    }
+
+    let mut estimate = None;
+    if let Some(position) = seeked_to {
+        if let Some((reported, _)) = player_state(lava, guild_id).await {
+            estimate = Some(Estimate {
+                reported,
+                position,
+                at: Instant::now(),
+            });
+        }
+    }
+    let task = tokio::spawn(run(lava.clone(), queue.clone(), guild_id, fade, estimate));
+    queue_lock.fade_task = Some(task);
+}
+
+pub async fn set_level(lava: &LavalinkClient, guild_id: GuildId, queue: &mut Queue, level: f64) {
+    queue.filters.set_fade(level);
+    if let Err(why) = lava
+        .set_filters(guild_id, queue.filters.to_lavalink())
+        .await
+    {
+        error!("Error setting filters in guild {}: {}", guild_id, why);
+    }
+}
+
+async fn run(
+    lava: LavalinkClient,
+    queue: Arc<Mutex<Queue>>,
+    guild_id: GuildId,
+    fade: Duration,
+    mut estimate: Option<Estimate>,
+) {
+    let step = fade / STEPS;
+    // fade in from where the previous track faded out to
+    let mut fading_in = true;
+    let mut prepared = false;
+    loop {
+        let remaining = match remaining(&lava, &queue, guild_id, &mut estimate).await {
+            Some(Some(remaining)) => remaining,
+            Some(None) => {
+                // paused, the level is kept until it's resumed
+                sleep(CHECK_INTERVAL).await;
+                continue;
+            }
+            None => return,
+        };
+        if !prepared && remaining <= fade + PREPARE_AHEAD {
+            // so the next track can start right away
+            Queue::prepare_next(&queue, &lava).await;
+            prepared = true;
+        }
+
+        // the fade out follows the position, so seeking back out of it restores the level
+        let target = (remaining.as_secs_f64() / fade.as_secs_f64()).min(1.0);
+        let mut queue_lock = queue.lock().await;
+        let current = queue_lock.filters.fade();
+        let level = if fading_in && current < target {
+            (current + 1.0 / f64::from(STEPS)).min(target)
+        } else {
+            target
+        };
+        if level >= target {
+            fading_in = false;
+        }
+        if level != current {
+            set_level(&lava, guild_id, &mut queue_lock, level).await;
+        }
+        drop(queue_lock);
+
+        if remaining.is_zero() {
+            return;
+        }
+        if level < 1.0 {
+            sleep(step).await;
+        } else {
+            sleep((remaining - fade).min(CHECK_INTERVAL)).await;
+        }
+    }
+}
+
+// None once nothing is playing, Some(None) while paused
+async fn remaining(
+    lava: &LavalinkClient,
+    queue: &Mutex<Queue>,
+    guild_id: GuildId,
+    estimate: &mut Option<Estimate>,
+) -> Option<Option<Duration>> {
+    let length = queue.lock().await.current_track.as_ref()?.length;
+    let (reported, paused) = player_state(lava, guild_id).await?;
+    if paused {
+        *estimate = None;
+        return Some(None);
+    }
+    let position = match *estimate {
+        Some(estimate) if estimate.reported == reported => {
+            estimate.position + estimate.at.elapsed()
+        }
+        _ => {
+            *estimate = Some(Estimate {
+                reported,
+                position: reported,
+                at: Instant::now(),
+            });
+            reported
+        }
+    };
+    Some(Some(length.saturating_sub(position)))
+}
+
+async fn player_state(lava: &LavalinkClient, guild_id: GuildId) -> Option<(Duration, bool)> {
+    let nodes = lava.nodes().await;
+    let node = nodes.get(&guild_id.0)?;
+    let info = node.now_playing.as_ref()?.track.info.as_ref()?;
+    Some((Duration::from_millis(info.position), node.is_paused))
+}
@@ -0,0 +1,72 @@
+use crate::music::queue::QueuedTrack;
+use serenity::model::id::UserId;
+use sqlx::PgPool;
+use std::time::Duration;
+
+pub const PAGE_SIZE: usize = 20;
+
+pub async fn add(
+    database: &PgPool,
+    user: UserId,
+    track: &QueuedTrack,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "INSERT INTO favourites (user_id, query, title, artist, length, lava_track)
+        VALUES ($1, $2, $3, $4, $5, NULLIF($6, ''))
+        ON CONFLICT (user_id, query) DO NOTHING",
+        user.0 as i64,
+        track.query,
+        track.title,
+        track.artist,
+        track.length.as_millis() as i64,
+        track.saved_lava_track()
+    )
+    .execute(database)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn count(database: &PgPool, user: UserId) -> Result<usize, sqlx::Error> {
+    let record = sqlx::query!(
+        "SELECT count(*) FROM favourites WHERE user_id = $1",
+        user.0 as i64
+    )
+    .fetch_one(database)
+    .await?;
+
+    Ok(record.count.unwrap_or(0) as usize)
+}
+
+pub async fn entries(
+    database: &PgPool,
+    user: UserId,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<QueuedTrack>, sqlx::Error> {
+    let rows = sqlx::query!(
+        "SELECT query, title, artist, length, lava_track
+        FROM favourites WHERE user_id = $1
+        ORDER BY id
+        LIMIT $2 OFFSET $3",
+        user.0 as i64,
+        limit as i64,
+        offset as i64
+    )
+    .fetch_all(database)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            QueuedTrack::from_saved(
+                row.query,
+                row.title,
+                row.artist,
+                Duration::from_millis(row.length as u64),
+                user,
+                row.lava_track,
+            )
+        })
+        .collect())
+}
@@ -0,0 +1,156 @@
+use lavalink_rs::model::{Band, Filters, Karaoke, LowPass, Rotation, Timescale, TremoloVibrato};
+
+pub const BANDS: usize = 15;
+
+#[derive(poise::ChoiceParameter, Clone, Copy)]
+pub enum Preset {
+    Bassboost,
+    Nightcore,
+    Vaporwave,
+    #[name = "8D"]
+    EightD,
+}
+
+#[derive(Clone, Default)]
+pub struct AudioFilters {
+    equalizer: [f64; BANDS],
+    timescale: Option<(f64, f64, f64)>,
+    rotation: Option<f64>,
+    tremolo: Option<(f64, f64)>,
+    vibrato: Option<(f64, f64)>,
+    karaoke: bool,
+    low_pass: Option<f64>,
+    fade: Option<f64>,
+}
+impl AudioFilters {
+    pub fn set_band(&mut self, band: usize, gain: f64) {
+        self.equalizer[band] = gain;
+    }
+
+    pub fn set_timescale(&mut self, speed: f64, pitch: f64, rate: f64) {
+        self.timescale = if speed == 1.0 && pitch == 1.0 && rate == 1.0 {
+            None
+        } else {
+            Some((speed, pitch, rate))
+        };
+    }
+
+    pub fn timescale(&self) -> (f64, f64, f64) {
+        self.timescale.unwrap_or((1.0, 1.0, 1.0))
+    }
+
+    pub fn set_rotation(&mut self, hz: f64) {
+        self.rotation = (hz > 0.0).then_some(hz);
+    }
+
+    pub fn set_tremolo(&mut self, frequency: f64, depth: f64) {
+        self.tremolo = (depth > 0.0).then_some((frequency, depth));
+    }
+
+    pub fn set_vibrato(&mut self, frequency: f64, depth: f64) {
+        self.vibrato = (depth > 0.0).then_some((frequency, depth));
+    }
+
+    pub fn set_karaoke(&mut self, enabled: bool) {
+        self.karaoke = enabled;
+    }
+
+    // smoothing of 1 lets everything through
+    pub fn set_low_pass(&mut self, smoothing: f64) {
+        self.low_pass = (smoothing > 1.0).then_some(smoothing);
+    }
+
+    // volume multiplier used by crossfade
+    pub fn set_fade(&mut self, level: f64) {
+        self.fade = (level < 1.0).then_some(level.max(0.0));
+    }
+
+    pub fn fade(&self) -> f64 {
+        self.fade.unwrap_or(1.0)
+    }
+
+    pub fn apply_preset(&mut self, preset: Preset) {
+        match preset {
+            Preset::Bassboost => {
+                for (band, gain) in [0.6, 0.45, 0.3, 0.15, 0.05].into_iter().enumerate() {
+                    self.equalizer[band] = gain;
+                }
+            }
+            Preset::Nightcore => self.set_timescale(1.25, 1.25, 1.0),
+            Preset::Vaporwave => self.set_timescale(0.8, 0.8, 1.0),
+            Preset::EightD => self.set_rotation(0.2),
+        }
+    }
+
+    pub fn description(&self) -> Option<String> {
+        let mut active = Vec::new();
+        if self.equalizer.iter().any(|gain| *gain != 0.0) {
+            let bands: Vec<String> = self
+                .equalizer
+                .iter()
+                .enumerate()
+                .filter(|(_, gain)| **gain != 0.0)
+                .map(|(band, gain)| format!("{band}: {gain:+}"))
+                .collect();
+            active.push(format!("Equalizer ({})", bands.join(", ")));
+        }
+        if let Some((speed, pitch, rate)) = self.timescale {
+            active.push(format!("Speed {speed}x, pitch {pitch}x, rate {rate}x"));
+        }
+        if let Some(hz) = self.rotation {
+            active.push(format!("Rotation {hz} Hz"));
+        }
+        if let Some((frequency, depth)) = self.tremolo {
+            active.push(format!("Tremolo {frequency} Hz, depth {depth}"));
+        }
+        if let Some((frequency, depth)) = self.vibrato {
+            active.push(format!("Vibrato {frequency} Hz, depth {depth}"));
+        }
+        if self.karaoke {
+            active.push("Karaoke".to_string());
+        }
+        if let Some(smoothing) = self.low_pass {
+            active.push(format!("Low-pass, smoothing {smoothing}"));
+        }
+
+        if active.is_empty() {
+            None
+        } else {
+            Some(active.join("\n"))
+        }
+    }
+
+    pub fn to_lavalink(&self) -> Filters {
+        Filters {
+            equalizer: Some(
+                self.equalizer
+                    .iter()
+                    .enumerate()
+                    .map(|(band, gain)| Band {
+                        band: band as u8,
+                        gain: *gain,
+                    })
+                    .collect(),
+            ),
+            karaoke: self.karaoke.then_some(Karaoke {
+                level: 1.0,
+                mono_level: 1.0,
+                filter_band: 220.0,
+                filter_width: 100.0,
+            }),
+            timescale: self
+                .timescale
+                .map(|(speed, pitch, rate)| Timescale { speed, pitch, rate }),
+            tremolo: self
+                .tremolo
+                .map(|(frequency, depth)| TremoloVibrato { frequency, depth }),
+            vibrato: self
+                .vibrato
+                .map(|(frequency, depth)| TremoloVibrato { frequency, depth }),
+            rotation: self.rotation.map(|rotation_hz| Rotation { rotation_hz }),
+            low_pass: self.low_pass.map(|smoothing| LowPass { smoothing }),
+            volume: self.fade,
+            ..Default::default()
+        }
+    }
+}
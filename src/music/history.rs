@@ -0,0 +1,82 @@
+use crate::music::queue::QueuedTrack;
+use serenity::model::id::{GuildId, UserId};
+use sqlx::PgPool;
+
+pub const PAGE_SIZE: usize = 20;
+
+pub struct HistoryEntry {
+    pub uri: String,
+    pub title: String,
+    pub requester: UserId,
+    pub started_at: i64,
+    pub skipped: bool,
+}
+
+pub async fn record(
+    database: &PgPool,
+    guild_id: GuildId,
+    track: &QueuedTrack,
+) -> Result<i64, sqlx::Error> {
+    let record = sqlx::query!(
+        "INSERT INTO play_history (guild_id, uri, title, requester)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id",
+        guild_id.0 as i64,
+        track.query,
+        track.title,
+        track.requester.0 as i64
+    )
+    .fetch_one(database)
+    .await?;
+
+    Ok(record.id)
+}
+
+pub async fn mark_skipped(database: &PgPool, id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query!("UPDATE play_history SET skipped = true WHERE id = $1", id)
+        .execute(database)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn count(database: &PgPool, guild_id: GuildId) -> Result<usize, sqlx::Error> {
+    let record = sqlx::query!(
+        "SELECT count(*) FROM play_history WHERE guild_id = $1",
+        guild_id.0 as i64
+    )
+    .fetch_one(database)
+    .await?;
+
+    Ok(record.count.unwrap_or(0) as usize)
+}
+
+pub async fn entries(
+    database: &PgPool,
+    guild_id: GuildId,
+    offset: usize,
+    limit: usize,
+) -> Result<Vec<HistoryEntry>, sqlx::Error> {
+    let rows = sqlx::query!(
+        "SELECT uri, title, requester, extract(epoch FROM started_at)::bigint AS started_at, skipped
+        FROM play_history WHERE guild_id = $1
+        ORDER BY id DESC
+        LIMIT $2 OFFSET $3",
+        guild_id.0 as i64,
+        limit as i64,
+        offset as i64
+    )
+    .fetch_all(database)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| HistoryEntry {
+            uri: row.uri,
+            title: row.title,
+            requester: UserId(row.requester as u64),
+            started_at: row.started_at.unwrap_or(0),
+            skipped: row.skipped,
+        })
+        .collect())
+}
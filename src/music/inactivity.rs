@@ -0,0 +1,131 @@
+use crate::{music::utils, shared_data::Guilds};
+use lavalink_rs::LavalinkClient;
+use serenity::{
+    model::id::{ChannelId, GuildId},
+    prelude::Context,
+};
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Default)]
+pub struct Inactivity {
+    pub idle_since: Option<Instant>,
+    alone_since: Option<Instant>,
+    paused: bool,
+}
+
+pub async fn run(ctx: Context, lava: LavalinkClient, guilds: Guilds) {
+    loop {
+        tokio::time::sleep(CHECK_INTERVAL).await;
+        let guild_ids: Vec<GuildId> = guilds.inner.lock().await.keys().copied().collect();
+        for guild_id in guild_ids {
+            check(&ctx, &lava, &guilds, guild_id).await;
+        }
+    }
+}
+
+pub async fn check(ctx: &Context, lava: &LavalinkClient, guilds: &Guilds, guild_id: GuildId) {
+    let manager = songbird::get(ctx).await.expect("Missing Songbird client");
+    if manager.get(guild_id).is_none() {
+        return;
+    }
+
+    let guild = match guilds.get(guild_id).await {
+        Ok(guild) => guild,
+        Err(why) => {
+            error!("Error getting guild {}: {}", guild_id, why);
+            return;
+        }
+    };
+    let (alone_timeout, idle_timeout, queue) = {
+        let guild_lock = guild.lock().await;
+        (
+            guild_lock.alone_timeout,
+            guild_lock.idle_timeout,
+            guild_lock.queue.clone(),
+        )
+    };
+
+    let listeners = match ctx.cache.guild(guild_id) {
+        Some(guild) => match guild
+            .voice_states
+            .get(&ctx.cache.current_user_id())
+            .and_then(|voice_state| voice_state.channel_id)
+        {
+            Some(channel_id) => utils::listeners(ctx, &guild, channel_id),
+            None => return,
+        },
+        None => return,
+    };
+
+    let mut queue_lock = queue.lock().await;
+    let now = Instant::now();
+    let channel_id = queue_lock.channel_id;
+    if listeners.is_empty() {
+        let alone_since = *queue_lock.inactivity.alone_since.get_or_insert(now);
+        // a track that was already paused by a user is left for them to resume
+        let playing = queue_lock.current_track.is_some()
+            && lava
+                .nodes()
+                .await
+                .get(&guild_id.0)
+                .map_or(false, |node| !node.is_paused);
+        if !queue_lock.inactivity.paused && playing {
+            if lava.pause(guild_id).await.is_ok() {
+                queue_lock.inactivity.paused = true;
+                notify(ctx, channel_id, "Everyone left the voice channel, pausing").await;
+            } else {
+                error!("Error pausing track");
+            }
+        }
+        if !alone_timeout.is_zero() && now.duration_since(alone_since) >= alone_timeout {
+            info!("Leaving empty voice channel in guild {guild_id}");
+            if let Err(why) = utils::disconnect(ctx, lava, &mut queue_lock, guild_id).await {
+                error!("Error leaving voice channel in guild {}: {}", guild_id, why);
+            }
+            notify(
+                ctx,
+                channel_id,
+                "Left the voice channel because nobody was listening",
+            )
+            .await;
+            return;
+        }
+    } else {
+        queue_lock.inactivity.alone_since = None;
+        if queue_lock.inactivity.paused {
+            queue_lock.inactivity.paused = false;
+            if lava.resume(guild_id).await.is_err() {
+                error!("Error resuming track");
+            }
+        }
+    }
+
+    if let Some(idle_since) = queue_lock.inactivity.idle_since {
+        if !idle_timeout.is_zero() && now.duration_since(idle_since) >= idle_timeout {
+            info!("Leaving idle voice channel in guild {guild_id}");
+            if let Err(why) = utils::disconnect(ctx, lava, &mut queue_lock, guild_id).await {
+                error!("Error leaving voice channel in guild {}: {}", guild_id, why);
+            }
+            notify(
+                ctx,
+                channel_id,
+                "Left the voice channel because the queue has been empty for a while",
+            )
+            .await;
+        }
+    }
+}
+
+async fn notify(ctx: &Context, channel_id: Option<ChannelId>, message: &str) {
+    if let Some(channel) = channel_id {
+        if let Err(why) = channel
+            .send_message(&ctx.http, |m| m.embed(|e| e.description(message)))
+            .await
+        {
+            error!("Error sending message: {:?}", why);
+        }
+    }
+}
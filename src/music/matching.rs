@@ -0,0 +1,204 @@
+use crate::{error::Error, music::queue::QueuedTrack};
+use lavalink_rs::{model::Track, LavalinkClient};
+use sqlx::PgPool;
+use std::{collections::HashSet, time::Duration};
+use tracing::error;
+
+const CANDIDATES: usize = 8;
+// below this even the best candidate is most likely a different version of the track
+const MIN_SCORE: i32 = 30;
+// versions that are rarely what was asked for, unless the spotify title says so
+const UNWANTED: [&str; 14] = [
+    "live",
+    "cover",
+    "remix",
+    "karaoke",
+    "instrumental",
+    "acoustic",
+    "slowed",
+    "sped",
+    "reverb",
+    "nightcore",
+    "8d",
+    "hour",
+    "hours",
+    "reaction",
+];
+
+struct Candidate {
+    track: Track,
+    isrc_match: bool,
+}
+
+// picks the youtube video that best matches a spotify track, the choice is cached per spotify id
+pub async fn find(
+    lava: &LavalinkClient,
+    database: &PgPool,
+    track: &QueuedTrack,
+) -> Result<Track, Error> {
+    let spotify = match &track.spotify {
+        Some(spotify) => spotify,
+        None => return Err("Not a Spotify track".into()),
+    };
+    match cached(database, &spotify.id).await {
+        Ok(Some(cached)) => return Ok(cached),
+        Ok(None) => {}
+        Err(why) => error!("Error getting cached match for {}: {}", spotify.id, why),
+    }
+
+    let mut candidates = Vec::new();
+    if let Some(isrc) = &spotify.isrc {
+        // topic channel uploads usually have the isrc in their description
+        let result = lava.search_tracks(format!("\"{isrc}\"")).await?;
+        candidates.extend(
+            result
+                .tracks
+                .into_iter()
+                .take(CANDIDATES)
+                .map(|track| Candidate {
+                    track,
+                    isrc_match: true,
+                }),
+        );
+    }
+    let result = lava.search_tracks(&track.query).await?;
+    candidates.extend(
+        result
+            .tracks
+            .into_iter()
+            .take(CANDIDATES)
+            .map(|track| Candidate {
+                track,
+                isrc_match: false,
+            }),
+    );
+
+    let mut seen = HashSet::new();
+    let best = candidates
+        .into_iter()
+        .filter(|candidate| match &candidate.track.info {
+            Some(info) => seen.insert(info.identifier.clone()),
+            None => false,
+        })
+        .map(|candidate| (score(track, &spotify.name, &candidate), candidate.track))
+        .max_by_key(|(score, _)| *score)
+        .filter(|(score, _)| *score >= MIN_SCORE)
+        .map(|(_, track)| track)
+        .ok_or("No matching videos found.")?;
+
+    if let Err(why) = save(database, &spotify.id, &best).await {
+        error!("Error caching match for {}: {}", spotify.id, why);
+    }
+    Ok(best)
+}
+
+fn score(track: &QueuedTrack, name: &str, candidate: &Candidate) -> i32 {
+    let info = candidate.track.info.as_ref().unwrap();
+    let mut score = 0;
+
+    // duration is the strongest signal, a few seconds off is normal
+    let length = Duration::from_millis(info.length);
+    let delta = if length > track.length {
+        length - track.length
+    } else {
+        track.length - length
+    };
+    score += match delta.as_secs() {
+        0..=2 => 40,
+        3..=5 => 30,
+        6..=10 => 15,
+        11..=30 => 0,
+        _ => -30,
+    };
+
+    let title = words(&info.title);
+    let author = info.author.to_lowercase();
+    let name_words = words(name);
+    if !name_words.is_empty() {
+        let found = name_words
+            .iter()
+            .filter(|word| title.contains(*word))
+            .count();
+        score += (30 * found / name_words.len()) as i32;
+    }
+    let artist = track.artist.to_lowercase();
+    let artist_words = words(&artist);
+    if !artist_words.is_empty()
+        && (author.contains(artist.trim()) || artist_words.iter().all(|word| title.contains(word)))
+    {
+        score += 15;
+    }
+    for unwanted in UNWANTED {
+        if title.contains(unwanted) && !name_words.contains(unwanted) {
+            score -= 25;
+        }
+    }
+
+    if candidate.isrc_match {
+        score += 20;
+    }
+    if author.ends_with(" - topic") {
+        score += 20;
+    }
+    let lowercase_title = info.title.to_lowercase();
+    if lowercase_title.contains("official audio") {
+        score += 10;
+    } else if lowercase_title.contains("official") {
+        score += 5;
+    }
+    if author.contains("vevo") {
+        score += 5;
+    }
+
+    score
+}
+
+fn words(text: &str) -> HashSet<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// for matches whose video can't be played anymore
+pub async fn forget(database: &PgPool, spotify_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM spotify_matches WHERE spotify_id = $1",
+        spotify_id
+    )
+    .execute(database)
+    .await?;
+
+    Ok(())
+}
+
+async fn cached(database: &PgPool, spotify_id: &str) -> Result<Option<Track>, sqlx::Error> {
+    let row = sqlx::query!(
+        "SELECT lava_track FROM spotify_matches WHERE spotify_id = $1",
+        spotify_id
+    )
+    .fetch_optional(database)
+    .await?;
+
+    Ok(row.and_then(|row| serde_json::from_str(&row.lava_track).ok()))
+}
+
+async fn save(database: &PgPool, spotify_id: &str, track: &Track) -> Result<(), sqlx::Error> {
+    let lava_track = match serde_json::to_string(track) {
+        Ok(lava_track) => lava_track,
+        Err(_) => return Ok(()),
+    };
+    sqlx::query!(
+        "INSERT INTO spotify_matches (spotify_id, lava_track)
+        VALUES ($1, $2)
+        ON CONFLICT (spotify_id) DO UPDATE
+            SET lava_track = $2",
+        spotify_id,
+        lava_track
+    )
+    .execute(database)
+    .await?;
+
+    Ok(())
+}
@@ -1,2 +1,14 @@
+pub mod autoplay;
+pub mod crossfade;
+pub mod favourites;
+pub mod filters;
+pub mod history;
+pub mod inactivity;
+pub mod matching;
+pub mod player;
 pub mod queue;
+pub mod resolver;
+pub mod saved;
+pub mod sponsorblock;
+pub mod spotify;
 pub mod utils;
@@ -0,0 +1,327 @@
+use crate::{
+    error::Error,
+    framework_functions,
+    interactions::{self, ComponentId},
+    music::{
+        queue::Queue,
+        utils::{self, Caller},
+    },
+    shared_data::Data,
+};
+use lavalink_rs::LavalinkClient;
+use serenity::{
+    builder::{CreateComponents, CreateEmbed},
+    http::Http,
+    model::{
+        application::{
+            component::ButtonStyle,
+            interaction::{
+                message_component::MessageComponentInteraction, InteractionResponseType,
+            },
+        },
+        id::{ChannelId, GuildId, MessageId},
+    },
+    prelude::Context,
+};
+use std::time::{Duration, Instant};
+use tracing::error;
+
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+const VOLUME_STEP: u16 = 10;
+
+#[derive(Default)]
+pub struct Player {
+    message: Option<(ChannelId, MessageId)>,
+    updated_at: Option<Instant>,
+}
+impl Player {
+    pub fn new(message: Option<(ChannelId, MessageId)>) -> Self {
+        Player {
+            message,
+            updated_at: None,
+        }
+    }
+
+    pub fn message(&self) -> Option<(ChannelId, MessageId)> {
+        self.message
+    }
+
+    pub fn is_message(&self, message_id: MessageId) -> bool {
+        self.message.map_or(false, |(_, id)| id == message_id)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum Action {
+    Pause,
+    Skip,
+    Stop,
+    Shuffle,
+    Loop,
+    VolumeDown,
+    VolumeUp,
+}
+impl Action {
+    const ALL: [Action; 7] = [
+        Action::Pause,
+        Action::Skip,
+        Action::Stop,
+        Action::Shuffle,
+        Action::Loop,
+        Action::VolumeDown,
+        Action::VolumeUp,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Pause => "pause",
+            Action::Skip => "skip",
+            Action::Stop => "stop",
+            Action::Shuffle => "shuffle",
+            Action::Loop => "loop",
+            Action::VolumeDown => "volume_down",
+            Action::VolumeUp => "volume_up",
+        }
+    }
+
+    // the slash command whose checks apply to the button
+    fn command(self) -> &'static str {
+        match self {
+            Action::Pause => "pause",
+            Action::Skip => "skip",
+            Action::Stop => "stop",
+            Action::Shuffle => "shuffle",
+            Action::Loop => "loop",
+            Action::VolumeDown | Action::VolumeUp => "volume",
+        }
+    }
+
+    fn emoji(self) -> char {
+        match self {
+            Action::Pause => '⏯',
+            Action::Skip => '⏭',
+            Action::Stop => '⏹',
+            Action::Shuffle => '🔀',
+            Action::Loop => '🔁',
+            Action::VolumeDown => '🔉',
+            Action::VolumeUp => '🔊',
+        }
+    }
+}
+
+async fn embed(lava: &LavalinkClient, guild_id: GuildId, queue: &Queue) -> CreateEmbed {
+    let (position, paused, volume) = match lava.nodes().await.get(&guild_id.0) {
+        Some(node) => (
+            node.now_playing
+                .as_ref()
+                .and_then(|track| track.track.info.as_ref())
+                .map_or(0, |info| info.position),
+            node.is_paused,
+            node.volume,
+        ),
+        None => (0, false, 100),
+    };
+
+    let mut embed = CreateEmbed::default();
+    embed.author(|a| a.name("Now playing"));
+    let track = match &queue.current_track {
+        Some(track) => track,
+        None => {
+            embed.title("The queue has ended.");
+            return embed;
+        }
+    };
+
+    let length = track.length.as_millis() as u64;
+    let info = track
+        .lava_track
+        .as_ref()
+        .and_then(|track| track.info.as_ref());
+    embed.title(&track.title).description(format!(
+        "{}\n{}\n{}/{}",
+        track.artist,
+        utils::progress_bar(position, length),
+        utils::length_to_string(position / 1000),
+        utils::length_to_string(length / 1000)
+    ));
+    if let Some(info) = info {
+        embed.url(&info.uri).thumbnail(format!(
+            "https://i.ytimg.com/vi/{}/hqdefault.jpg",
+            info.identifier
+        ));
+    }
+    embed
+        .field("Requested by", format!("<@{}>", track.requester.0), true)
+        .field("Loop", queue.loop_mode(track.requester), true)
+        .field("Volume", volume, true);
+    if paused {
+        embed.footer(|f| f.text("Paused"));
+    }
+
+    embed
+}
+
+fn components(
+    c: &mut CreateComponents,
+    guild_id: GuildId,
+    disabled: bool,
+) -> &mut CreateComponents {
+    c.create_action_row(|r| {
+        for action in &Action::ALL[..5] {
+            r.create_button(|b| {
+                b.style(ButtonStyle::Secondary)
+                    .emoji(action.emoji())
+                    .custom_id(ComponentId::Player(guild_id, *action))
+                    .disabled(disabled)
+            });
+        }
+        r
+    })
+    .create_action_row(|r| {
+        for action in &Action::ALL[5..] {
+            r.create_button(|b| {
+                b.style(ButtonStyle::Secondary)
+                    .emoji(action.emoji())
+                    .custom_id(ComponentId::Player(guild_id, *action))
+                    .disabled(disabled)
+            });
+        }
+        r.create_button(|b| {
+            b.style(ButtonStyle::Secondary)
+                .emoji('❤')
+                .custom_id(ComponentId::Like(guild_id))
+                .disabled(disabled)
+        })
+    })
+}
+
+pub async fn update(http: &Http, lava: &LavalinkClient, guild_id: GuildId, queue: &mut Queue) {
+    let channel_id = match queue.channel_id {
+        Some(channel_id) => channel_id,
+        None => return,
+    };
+    let embed = embed(lava, guild_id, queue).await;
+    let disabled = queue.current_track.is_none();
+    queue.player.updated_at = Some(Instant::now());
+
+    if let Some((message_channel_id, message_id)) = queue.player.message {
+        if message_channel_id == channel_id {
+            match channel_id
+                .edit_message(http, message_id, |m| {
+                    m.set_embed(embed.clone())
+                        .components(|c| components(c, guild_id, disabled))
+                })
+                .await
+            {
+                Ok(_) => return,
+                Err(why) => error!("Error editing player message: {:?}", why),
+            }
+        }
+    }
+
+    match channel_id
+        .send_message(http, |m| {
+            m.set_embed(embed)
+                .components(|c| components(c, guild_id, disabled))
+        })
+        .await
+    {
+        Ok(message) => {
+            queue.player.message = Some((channel_id, message.id));
+            queue.save().await;
+        }
+        Err(why) => error!("Error sending message: {:?}", why),
+    }
+}
+
+pub async fn progress(http: &Http, lava: &LavalinkClient, guild_id: GuildId, queue: &mut Queue) {
+    let recently_updated = queue
+        .player
+        .updated_at
+        .map_or(false, |updated_at| updated_at.elapsed() < PROGRESS_INTERVAL);
+    if queue.player.message.is_none() || recently_updated {
+        return;
+    }
+    update(http, lava, guild_id, queue).await;
+}
+
+pub async fn handle_button(
+    ctx: &Context,
+    mci: &MessageComponentInteraction,
+    data: &Data,
+    guild_id: GuildId,
+    action: Action,
+) -> Result<(), Error> {
+    let caller = match Caller::from_component(ctx, data, mci) {
+        Some(caller) if caller.guild_id == guild_id => caller,
+        _ => return Ok(()),
+    };
+    let queue = data.guilds.get_queue(guild_id).await?;
+    if !queue.lock().await.player.is_message(mci.message.id) {
+        return Err("This player is no longer active".into());
+    }
+
+    if let Some(message) = run(&caller, action).await? {
+        return interactions::respond(ctx, mci, message).await;
+    }
+    let mut queue_lock = queue.lock().await;
+    let embed = embed(&data.lavalink, guild_id, &queue_lock).await;
+    let disabled = queue_lock.current_track.is_none();
+    queue_lock.player.updated_at = Some(Instant::now());
+    mci.create_interaction_response(&ctx.http, |r| {
+        r.kind(InteractionResponseType::UpdateMessage)
+            .interaction_response_data(|d| {
+                d.set_embed(embed)
+                    .components(|c| components(c, guild_id, disabled))
+            })
+    })
+    .await?;
+
+    Ok(())
+}
+
+async fn run(caller: &Caller<'_>, action: Action) -> Result<Option<String>, Error> {
+    framework_functions::text_channel_check(caller).await?;
+    framework_functions::dj_check(caller, action.command()).await?;
+    let (lava, queue) = caller.voice_check().await?;
+    let guild_id = caller.guild_id;
+    let (paused, volume) = lava
+        .nodes()
+        .await
+        .get(&guild_id.0)
+        .map_or((false, 100), |node| (node.is_paused, node.volume));
+
+    match action {
+        Action::Pause if paused => lava.resume(guild_id).await?,
+        Action::Pause => lava.pause(guild_id).await?,
+        Action::Skip => return utils::skip(caller, lava, &queue).await.map(Some),
+        Action::Stop => {
+            if queue.lock().await.stop(lava).await.is_err() {
+                return Err("Error stoping".into());
+            }
+        }
+        Action::Shuffle => {
+            queue.lock().await.shuffle(caller.user_id).await;
+            return Ok(Some("Queue shuffled".to_string()));
+        }
+        Action::Loop => {
+            let mut queue_lock = queue.lock().await;
+            let mode = queue_lock.loop_mode(caller.user_id).next();
+            queue_lock.set_loop_mode(mode, caller.user_id).await;
+        }
+        Action::VolumeDown => {
+            lava.volume(guild_id, volume.saturating_sub(VOLUME_STEP))
+                .await?
+        }
+        Action::VolumeUp => {
+            lava.volume(guild_id, (volume + VOLUME_STEP).min(1000))
+                .await?
+        }
+    }
+
+    Ok(None)
+}
@@ -1,17 +1,41 @@
+use super::{
+    filters::AudioFilters, inactivity::Inactivity, matching, player::Player, sponsorblock::Segments,
+};
 use lavalink_rs::{error::LavalinkResult, model::Track, LavalinkClient};
 use rand::prelude::SliceRandom;
 use serenity::{
-    http::Http,
-    model::id::{ChannelId, GuildId, UserId},
+    model::id::{ChannelId, GuildId, MessageId, UserId},
     prelude::Mutex,
 };
+use sqlx::PgPool;
 use std::{
-    collections::{HashMap, VecDeque},
-    sync::Arc,
-    time::Duration,
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
+use tokio::{sync::watch, task::JoinHandle, time::sleep};
 use tracing::error;
 
+const PREVIOUS_TRACKS: usize = 50;
+// how long saving waits for more changes to the queue
+const SAVE_DELAY: Duration = Duration::from_secs(2);
+// player updates only save the position once it moved this far from the saved one
+const POSITION_SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+// identifies a queued track while it's being resolved in the background
+static NEXT_TRACK_ID: AtomicU64 = AtomicU64::new(0);
+
+// what the track was queued from, so it can be matched more precisely than by the query
+#[derive(Clone)]
+pub struct SpotifyInfo {
+    pub id: String,
+    pub name: String,
+    pub isrc: Option<String>,
+}
+
 #[derive(Clone)]
 pub struct QueuedTrack {
     pub query: String,
@@ -20,6 +44,14 @@ pub struct QueuedTrack {
     pub length: Duration,
     pub lava_track: Option<Track>,
     pub requester: UserId,
+    // where playback starts, from the t= parameter of the link
+    pub start_time: Duration,
+    pub spotify: Option<SpotifyInfo>,
+    pub id: u64,
+    // set when no video could be found for the track
+    pub unresolvable: bool,
+    // searches that failed for other reasons, like lavalink being unreachable
+    pub failed_searches: u8,
 }
 impl QueuedTrack {
     pub fn new(query: String, artist: String, length: Duration, requester: UserId) -> Self {
@@ -30,6 +62,11 @@ impl QueuedTrack {
             length,
             lava_track: None,
             requester,
+            start_time: Duration::ZERO,
+            spotify: None,
+            id: NEXT_TRACK_ID.fetch_add(1, Ordering::Relaxed),
+            unresolvable: false,
+            failed_searches: 0,
         }
     }
 
@@ -42,18 +79,90 @@ impl QueuedTrack {
             length: Duration::from_millis(info.length),
             lava_track: Some(lava_track),
             requester,
+            start_time: Duration::ZERO,
+            spotify: None,
+            id: NEXT_TRACK_ID.fetch_add(1, Ordering::Relaxed),
+            unresolvable: false,
+            failed_searches: 0,
+        }
+    }
+
+    pub fn from_saved(
+        query: String,
+        title: String,
+        artist: String,
+        length: Duration,
+        requester: UserId,
+        lava_track: Option<String>,
+    ) -> Self {
+        QueuedTrack {
+            query,
+            title,
+            artist,
+            length,
+            lava_track: lava_track.and_then(|track| serde_json::from_str(&track).ok()),
+            requester,
+            start_time: Duration::ZERO,
+            spotify: None,
+            id: NEXT_TRACK_ID.fetch_add(1, Ordering::Relaxed),
+            unresolvable: false,
+            failed_searches: 0,
         }
     }
 
-    pub async fn init(&mut self, lava: &LavalinkClient) -> Result<Track, crate::error::Error> {
+    // the t= offset only applies the first time the track plays, not when it's replayed
+    pub fn take_start_time(&mut self) -> Duration {
+        std::mem::take(&mut self.start_time)
+    }
+
+    pub fn display_title(&self) -> String {
+        if self.unresolvable {
+            format!("{} (not found)", self.title)
+        } else {
+            self.title.clone()
+        }
+    }
+
+    pub fn saved_lava_track(&self) -> String {
+        self.lava_track
+            .as_ref()
+            .and_then(|track| serde_json::to_string(track).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn identifier(&self) -> Option<&str> {
+        self.lava_track
+            .as_ref()
+            .and_then(|track| track.info.as_ref())
+            .map(|info| info.identifier.as_str())
+    }
+
+    pub fn normalized_query(&self) -> String {
+        self.query
+            .to_lowercase()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    pub async fn init(
+        &mut self,
+        lava: &LavalinkClient,
+        database: &PgPool,
+    ) -> Result<Track, crate::error::Error> {
         match &self.lava_track {
             Some(track) => Ok(track.clone()),
+            None if self.unresolvable => Err("No matching videos found.".into()),
             None => {
-                let query_result = lava.auto_search_tracks(&self.query).await?;
-                if query_result.tracks.is_empty() {
-                    return Err("No matching videos found.".into());
-                }
-                let track = query_result.tracks[0].clone();
+                let track = if self.spotify.is_some() {
+                    matching::find(lava, database, self).await?
+                } else {
+                    let query_result = lava.auto_search_tracks(&self.query).await?;
+                    if query_result.tracks.is_empty() {
+                        return Err("No matching videos found.".into());
+                    }
+                    query_result.tracks[0].clone()
+                };
                 let info = track.info.clone().unwrap();
                 self.query = info.uri;
                 self.title = info.title;
@@ -72,6 +181,33 @@ pub enum LoopModes {
     Song,
     Queue,
 }
+impl From<LoopModes> for i16 {
+    fn from(mode: LoopModes) -> Self {
+        match mode {
+            LoopModes::None => 0,
+            LoopModes::Song => 1,
+            LoopModes::Queue => 2,
+        }
+    }
+}
+impl LoopModes {
+    pub fn next(self) -> Self {
+        match self {
+            LoopModes::None => LoopModes::Song,
+            LoopModes::Song => LoopModes::Queue,
+            LoopModes::Queue => LoopModes::None,
+        }
+    }
+}
+impl From<i16> for LoopModes {
+    fn from(mode: i16) -> Self {
+        match mode {
+            1 => LoopModes::Song,
+            2 => LoopModes::Queue,
+            _ => LoopModes::None,
+        }
+    }
+}
 // impl std::fmt::Display for LoopModes {
 //     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 //         match self {
@@ -82,6 +218,92 @@ pub enum LoopModes {
 //     }
 // }
 
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum Placement {
+    Back,
+    Next,
+    Now,
+}
+
+#[derive(PartialEq, poise::ChoiceParameter, Clone, Copy)]
+pub enum DuplicatePolicy {
+    Allow,
+    Warn,
+    Reject,
+}
+impl From<DuplicatePolicy> for i16 {
+    fn from(policy: DuplicatePolicy) -> Self {
+        match policy {
+            DuplicatePolicy::Allow => 0,
+            DuplicatePolicy::Warn => 1,
+            DuplicatePolicy::Reject => 2,
+        }
+    }
+}
+impl From<i16> for DuplicatePolicy {
+    fn from(policy: i16) -> Self {
+        match policy {
+            1 => DuplicatePolicy::Warn,
+            2 => DuplicatePolicy::Reject,
+            _ => DuplicatePolicy::Allow,
+        }
+    }
+}
+
+#[derive(Default)]
+struct DuplicateIndex {
+    identifiers: HashSet<String>,
+    queries: HashSet<String>,
+}
+impl DuplicateIndex {
+    fn contains(&self, track: &QueuedTrack) -> bool {
+        track
+            .identifier()
+            .map_or(false, |identifier| self.identifiers.contains(identifier))
+            || self.queries.contains(&track.normalized_query())
+    }
+
+    fn insert(&mut self, track: &QueuedTrack) {
+        if let Some(identifier) = track.identifier() {
+            self.identifiers.insert(identifier.to_string());
+        }
+        self.queries.insert(track.normalized_query());
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct QueueLimits {
+    pub max_queue_size: usize,
+    pub max_user_tracks: usize,
+    pub max_track_length: Duration,
+    pub max_playlist_size: usize,
+}
+impl QueueLimits {
+    pub fn allows_length(&self, length: Duration) -> bool {
+        self.max_track_length.is_zero() || length <= self.max_track_length
+    }
+
+    pub fn check_length(&self, length: Duration) -> Result<(), crate::error::Error> {
+        if self.allows_length(length) {
+            return Ok(());
+        }
+        Err(crate::error::Error::Command(format!(
+            "Tracks can be at most {} long on this server",
+            crate::music::utils::length_to_string(self.max_track_length.as_secs())
+        )))
+    }
+
+    pub fn check_playlist_size(&self, size: usize) -> Result<(), crate::error::Error> {
+        if self.max_playlist_size == 0 || size <= self.max_playlist_size {
+            return Ok(());
+        }
+        Err(crate::error::Error::Command(format!(
+            "Playlists can have at most {} tracks on this server",
+            self.max_playlist_size
+        )))
+    }
+}
+
 struct UserQueue {
     tracks: VecDeque<QueuedTrack>,
     loop_mode: LoopModes,
@@ -98,55 +320,356 @@ impl UserQueue {
 pub struct Queue {
     guild_id: GuildId,
     pub channel_id: Option<ChannelId>,
+    pub voice_channel_id: Option<ChannelId>,
     loop_mode: LoopModes,
     skipped: bool,
+    stopped: bool,
+    rearranged: bool,
     tracks: VecDeque<QueuedTrack>,
     pub current_track: Option<QueuedTrack>,
+    previous_tracks: VecDeque<QueuedTrack>,
     round_robin: bool,
     users: VecDeque<UserId>,
     user_queues: HashMap<UserId, UserQueue>,
+    skip_votes: HashSet<UserId>,
+    pub inactivity: Inactivity,
+    pub history_id: Option<i64>,
+    pub player: Player,
+    pub filters: AudioFilters,
+    pub fade_task: Option<JoinHandle<()>>,
+    pub segments: Option<Segments>,
+    pub resolving: bool,
+    saver: watch::Sender<Option<Arc<SavedQueue>>>,
+    saved_position: Option<Duration>,
+    database: PgPool,
 }
 impl Queue {
     pub fn new(
         guild_id: GuildId,
         channel_id: Option<ChannelId>,
         round_robin: bool,
+        database: PgPool,
     ) -> Arc<Mutex<Self>> {
+        let (saver, saved) = watch::channel(None);
+        tokio::spawn(write_changes(database.clone(), saved));
         Arc::new(Mutex::new(Queue {
             guild_id,
             channel_id,
+            voice_channel_id: None,
             loop_mode: LoopModes::None,
             skipped: false,
+            stopped: false,
+            rearranged: false,
             tracks: VecDeque::default(),
             current_track: None,
+            previous_tracks: VecDeque::default(),
             round_robin,
             users: VecDeque::default(),
             user_queues: HashMap::default(),
+            skip_votes: HashSet::default(),
+            inactivity: Inactivity::default(),
+            history_id: None,
+            player: Player::default(),
+            filters: AudioFilters::default(),
+            fade_task: None,
+            segments: None,
+            resolving: false,
+            saver,
+            saved_position: None,
+            database,
         }))
     }
 
+    pub async fn load(guild_id: GuildId, round_robin: bool, database: PgPool) -> Arc<Mutex<Self>> {
+        let queue = Self::new(guild_id, None, round_robin, database);
+        {
+            let mut queue_lock = queue.lock().await;
+            if let Err(why) = queue_lock.restore().await {
+                error!("Error loading queue in guild {}: {}", guild_id, why);
+            }
+        }
+        queue
+    }
+
+    async fn restore(&mut self) -> Result<(), sqlx::Error> {
+        let guild_id = self.guild_id.0 as i64;
+        let state = match sqlx::query!(
+            "SELECT text_channel_id, voice_channel_id, loop_mode, users, user_loop_modes,
+                player_channel_id, player_message_id
+            FROM queues WHERE guild_id = $1",
+            guild_id
+        )
+        .fetch_optional(&self.database)
+        .await?
+        {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+
+        self.channel_id = state.text_channel_id.map(|id| ChannelId(id as u64));
+        self.voice_channel_id = state.voice_channel_id.map(|id| ChannelId(id as u64));
+        self.loop_mode = state.loop_mode.into();
+        if let (Some(channel_id), Some(message_id)) =
+            (state.player_channel_id, state.player_message_id)
+        {
+            self.player = Player::new(Some((
+                ChannelId(channel_id as u64),
+                MessageId(message_id as u64),
+            )));
+        }
+        for (user, loop_mode) in state.users.iter().zip(state.user_loop_modes.iter()) {
+            let user = UserId(*user as u64);
+            let mut queue = UserQueue::new();
+            queue.loop_mode = (*loop_mode).into();
+            self.user_queues.insert(user, queue);
+            self.users.push_back(user);
+        }
+
+        let rows = sqlx::query!(
+            "SELECT ordinal, query, title, artist, length, requester, lava_track,
+                spotify_id, spotify_name, isrc
+            FROM queued_tracks WHERE guild_id = $1 ORDER BY ordinal",
+            guild_id
+        )
+        .fetch_all(&self.database)
+        .await?;
+
+        for row in rows {
+            let mut track = QueuedTrack::from_saved(
+                row.query,
+                row.title,
+                row.artist,
+                Duration::from_millis(row.length as u64),
+                UserId(row.requester as u64),
+                row.lava_track,
+            );
+            if let (Some(id), Some(name)) = (row.spotify_id, row.spotify_name) {
+                track.spotify = Some(SpotifyInfo {
+                    id,
+                    name,
+                    isrc: row.isrc,
+                });
+            }
+            if row.ordinal == 0 {
+                self.current_track = Some(track);
+            } else if self.round_robin {
+                let user = track.requester;
+                if let Some(queue) = self.user_queues.get_mut(&user) {
+                    queue.tracks.push_back(track);
+                } else {
+                    let mut queue = UserQueue::new();
+                    queue.tracks.push_back(track);
+                    self.user_queues.insert(user, queue);
+                    self.users.push_back(user);
+                }
+            } else {
+                self.tracks.push_back(track);
+            }
+        }
+
+        Ok(())
+    }
+
+    // the queue is written in the background, changes made shortly after each other are written once
+    pub async fn save(&self) {
+        // only fails once the queue is dropped
+        let _ = self.saver.send(Some(Arc::new(self.saved())));
+    }
+
+    fn queued_tracks(&self) -> Vec<&QueuedTrack> {
+        if self.round_robin {
+            self.users
+                .iter()
+                .filter_map(|user| self.user_queues.get(user))
+                .flat_map(|queue| queue.tracks.iter())
+                .collect()
+        } else {
+            self.tracks.iter().collect()
+        }
+    }
+
+    pub fn all_tracks(&self) -> Vec<QueuedTrack> {
+        self.current_track
+            .iter()
+            .chain(self.queued_tracks())
+            .cloned()
+            .collect()
+    }
+
+    // what save writes, taken while the queue is locked so writing it doesn't need the lock
+    fn saved(&self) -> SavedQueue {
+        let mut saved = SavedQueue {
+            guild_id: self.guild_id.0 as i64,
+            text_channel_id: self.channel_id.map(|id| id.0 as i64),
+            voice_channel_id: self.voice_channel_id.map(|id| id.0 as i64),
+            loop_mode: self.loop_mode.into(),
+            users: self.users.iter().map(|user| user.0 as i64).collect(),
+            user_loop_modes: self
+                .users
+                .iter()
+                .map(|user| {
+                    self.user_queues
+                        .get(user)
+                        .map(|queue| queue.loop_mode)
+                        .unwrap_or(LoopModes::None)
+                        .into()
+                })
+                .collect(),
+            player_channel_id: None,
+            player_message_id: None,
+            ordinals: Vec::new(),
+            queries: Vec::new(),
+            titles: Vec::new(),
+            artists: Vec::new(),
+            lengths: Vec::new(),
+            requesters: Vec::new(),
+            lava_tracks: Vec::new(),
+            spotify_ids: Vec::new(),
+            spotify_names: Vec::new(),
+            isrcs: Vec::new(),
+        };
+        if let Some((channel_id, message_id)) = self.player.message() {
+            saved.player_channel_id = Some(channel_id.0 as i64);
+            saved.player_message_id = Some(message_id.0 as i64);
+        }
+
+        let queued = self.queued_tracks();
+        let tracks = self.current_track.iter().map(|track| (0, track)).chain(
+            queued
+                .into_iter()
+                .enumerate()
+                .map(|(i, track)| (i as i32 + 1, track)),
+        );
+        for (ordinal, track) in tracks {
+            saved.ordinals.push(ordinal);
+            saved.queries.push(track.query.clone());
+            saved.titles.push(track.title.clone());
+            saved.artists.push(track.artist.clone());
+            saved.lengths.push(track.length.as_millis() as i64);
+            saved.requesters.push(track.requester.0 as i64);
+            saved.lava_tracks.push(track.saved_lava_track());
+            let spotify = track.spotify.as_ref();
+            saved.spotify_ids.push(
+                spotify
+                    .map(|spotify| spotify.id.clone())
+                    .unwrap_or_default(),
+            );
+            saved.spotify_names.push(
+                spotify
+                    .map(|spotify| spotify.name.clone())
+                    .unwrap_or_default(),
+            );
+            saved.isrcs.push(
+                spotify
+                    .and_then(|spotify| spotify.isrc.clone())
+                    .unwrap_or_default(),
+            );
+        }
+        saved
+    }
+
+    pub async fn save_position(&mut self, position: Duration) {
+        if let Err(why) = sqlx::query!(
+            "UPDATE queues SET position = $2 WHERE guild_id = $1",
+            self.guild_id.0 as i64,
+            position.as_millis() as i64
+        )
+        .execute(&self.database)
+        .await
+        {
+            error!("Error saving position in guild {}: {}", self.guild_id, why);
+            return;
+        }
+        self.saved_position = Some(position);
+    }
+
+    // for the position lavalink reports while playing, which doesn't need to be exact
+    pub async fn save_progress(&mut self, position: Duration) {
+        let moved = self.saved_position.map_or(true, |saved| {
+            position.max(saved) - position.min(saved) >= POSITION_SAVE_INTERVAL
+        });
+        if moved {
+            self.save_position(position).await;
+        }
+    }
+
+    pub async fn resume(
+        &mut self,
+        lava: &LavalinkClient,
+        position: Duration,
+    ) -> Result<(), crate::error::Error> {
+        if let Some(track) = &mut self.current_track {
+            let lava_track = track.init(lava, &self.database).await?;
+            lava.play(self.guild_id, lava_track)
+                .start_time(position)
+                .queue()
+                .await?;
+        }
+
+        Ok(())
+    }
+
     pub async fn enqueue(
+        &mut self,
+        track: QueuedTrack,
+        lava: LavalinkClient,
+    ) -> Result<(), crate::error::Error> {
+        self.enqueue_at(track, Placement::Back, lava).await
+    }
+
+    pub async fn enqueue_at(
         &mut self,
         mut track: QueuedTrack,
+        placement: Placement,
         lava: LavalinkClient,
     ) -> Result<(), crate::error::Error> {
         if self.current_track.is_none() {
-            let lava_track = track.init(&lava).await?;
-            lava.play(self.guild_id, lava_track).queue().await?;
+            let lava_track = track.init(&lava, &self.database).await?;
+            lava.play(self.guild_id, lava_track)
+                .start_time(track.take_start_time())
+                .queue()
+                .await?;
             self.current_track = Some(track);
-        } else if self.round_robin {
+            self.inactivity.idle_since = None;
+            self.save().await;
+            return Ok(());
+        }
+
+        if self.round_robin {
             let user = track.requester;
-            let queue = self.user_queues.get_mut(&user);
-            if let Some(queue) = queue {
-                queue.tracks.push_back(track);
-            } else {
-                let mut queue = UserQueue::new();
-                queue.tracks.push_back(track);
-                self.user_queues.insert(user, queue);
-                self.users.push_back(user);
+            match placement {
+                Placement::Back => {
+                    let queue = self.user_queues.get_mut(&user);
+                    if let Some(queue) = queue {
+                        queue.tracks.push_back(track);
+                    } else {
+                        let mut queue = UserQueue::new();
+                        queue.tracks.push_back(track);
+                        self.user_queues.insert(user, queue);
+                        self.users.push_back(user);
+                    }
+                }
+                Placement::Next => self.push_user_front(track),
+                Placement::Now => {
+                    self.advance_user(None);
+                    if let Some(current) = self.current_track.clone() {
+                        self.push_previous(current);
+                    }
+                    self.push_user_front(track);
+                    self.users.retain(|id| *id != user);
+                    self.users.push_front(user);
+                    self.rearranged = true;
+                }
             }
-        } else {
+        } else if placement == Placement::Back {
             self.tracks.push_back(track);
+        } else {
+            self.tracks.push_front(track);
+        }
+        self.save().await;
+
+        if placement == Placement::Now {
+            self.skip(lava).await?;
         }
 
         Ok(())
@@ -156,18 +679,47 @@ impl Queue {
         &mut self,
         mut tracks: Vec<QueuedTrack>,
         lava: LavalinkClient,
-    ) -> Result<(), ()> {
+        skip_duplicates: bool,
+    ) -> Result<usize, ()> {
+        let mut dropped = 0;
+        if skip_duplicates {
+            let mut index = self.duplicate_index();
+            let total = tracks.len();
+            tracks.retain(|track| {
+                if index.contains(track) {
+                    return false;
+                }
+                index.insert(track);
+                true
+            });
+            dropped = total - tracks.len();
+        }
+
+        if tracks.is_empty() {
+            return Ok(dropped);
+        }
         if self.current_track.is_none() {
             let mut track = tracks.remove(0);
-            if let Ok(lava_track) = track.init(&lava).await {
-                if lava.play(self.guild_id, lava_track).queue().await.is_err() {
+            if let Ok(lava_track) = track.init(&lava, &self.database).await {
+                if lava
+                    .play(self.guild_id, lava_track)
+                    .start_time(track.take_start_time())
+                    .queue()
+                    .await
+                    .is_err()
+                {
                     return Err(());
                 };
                 self.current_track = Some(track);
+                self.inactivity.idle_since = None;
             } else {
                 return Err(());
             }
         }
+        if tracks.is_empty() {
+            self.save().await;
+            return Ok(dropped);
+        }
         if self.round_robin {
             let user = tracks[0].requester;
             let queue = self.user_queues.get_mut(&user);
@@ -182,103 +734,156 @@ impl Queue {
         } else {
             self.tracks.append(&mut tracks.into());
         }
+        self.save().await;
 
-        Ok(())
+        Ok(dropped)
     }
 
-    pub fn tracklist(&self, mut page: usize) -> (String, Option<(usize, usize, usize, Duration)>) {
-        let mut tracklist = String::new();
-        let mut info = None;
-        if self.round_robin {
-            if self.users.is_empty() {
-                tracklist += "The queue is empty.";
-            } else {
-                let mut len = 0;
-                for queue in self.user_queues.values() {
-                    len += queue.tracks.len();
-                }
-                let page_count = (len as f32 / 20.).ceil() as usize;
-                if page > page_count - 1 {
-                    page = page_count - 1;
-                }
-                let mut length = Duration::new(0, 0);
-
-                let mut users = self.users.clone();
-                let cur_user = users.pop_front().unwrap();
-                users.push_back(cur_user);
-
-                let mut track_num = 1;
-                let mut user_index = 0;
-                let mut user_tracks = vec![0; users.len()];
-                while track_num <= len {
-                    let user = users[user_index];
-                    let queue = self.user_queues.get(&user).unwrap();
-                    if user_tracks[user_index] < queue.tracks.len() {
-                        let track = &queue.tracks[user_tracks[user_index]];
-                        if track_num > page * 20 && track_num <= page * 20 + 20 {
-                            let title = &track.title;
-                            let duration =
-                                crate::music::utils::length_to_string(track.length.as_secs());
-                            let requester = track.requester.0;
-
-                            tracklist += &format!(
-                                "{}. {} ({}) - <@{}>\n",
-                                track_num, title, duration, requester
-                            );
-                        }
-                        length += track.length;
-                        track_num += 1;
-                        user_tracks[user_index] += 1;
-                    }
-                    user_index += 1;
-                    if user_index > users.len() - 1 {
-                        user_index = 0;
-                    }
+    fn duplicate_index(&self) -> DuplicateIndex {
+        let mut index = DuplicateIndex::default();
+        let queued = self.tracks.iter().chain(
+            self.user_queues
+                .values()
+                .flat_map(|queue| queue.tracks.iter()),
+        );
+        for track in self.current_track.iter().chain(queued) {
+            index.insert(track);
+        }
+        index
+    }
+
+    pub fn contains(&self, track: &QueuedTrack) -> bool {
+        self.duplicate_index().contains(track)
+    }
+
+    fn round_robin_users(&self) -> Vec<UserId> {
+        let mut users: Vec<UserId> = self
+            .users
+            .iter()
+            .filter(|user| self.user_queues.contains_key(user))
+            .copied()
+            .collect();
+        // the first user is the one whose track is playing
+        if !users.is_empty() {
+            users.rotate_left(1);
+        }
+        users
+    }
+
+    fn round_robin_order(&self) -> Vec<(UserId, usize)> {
+        let users = self.round_robin_users();
+        let mut order = Vec::new();
+        let mut round = 0;
+        loop {
+            let len = order.len();
+            for user in &users {
+                if round < self.user_queues[user].tracks.len() {
+                    order.push((*user, round));
                 }
-                info = Some((page, page_count, track_num - 1, length));
             }
-        } else if self.tracks.is_empty() {
-            tracklist += "The queue is empty.";
-        } else {
-            let page_count = (self.tracks.len() as f32 / 20.).ceil() as usize;
-            if page > page_count - 1 {
-                page = page_count - 1;
-            }
-            let mut length = Duration::new(0, 0);
-            for (i, track) in self.tracks.iter().enumerate() {
-                length += track.length;
-                if i >= page * 20 && i < page * 20 + 20 {
-                    let title = &track.title;
-                    let duration = crate::music::utils::length_to_string(track.length.as_secs());
-                    let requester = track.requester.0;
-                    tracklist +=
-                        &format!("{}. {} ({}) - <@{}>\n", i + 1, title, duration, requester);
-                }
+            if order.len() == len {
+                break;
             }
-            info = Some((page, page_count, self.tracks.len(), length));
+            round += 1;
         }
-        (tracklist, info)
+        order
+    }
+
+    fn ordered_tracks(&self) -> Vec<&QueuedTrack> {
+        if self.round_robin {
+            self.round_robin_order()
+                .into_iter()
+                .map(|(user, index)| &self.user_queues[&user].tracks[index])
+                .collect()
+        } else {
+            self.tracks.iter().collect()
+        }
+    }
+
+    pub fn tracklist(
+        &self,
+        mut page: usize,
+        page_size: usize,
+    ) -> (String, Option<(usize, usize, usize, Duration)>) {
+        let tracks = self.ordered_tracks();
+        if tracks.is_empty() {
+            return ("The queue is empty.".to_string(), None);
+        }
+        let page_count = (tracks.len() as f32 / page_size as f32).ceil() as usize;
+        if page > page_count - 1 {
+            page = page_count - 1;
+        }
+
+        let mut tracklist = String::new();
+        for (i, track) in tracks
+            .iter()
+            .enumerate()
+            .skip(page * page_size)
+            .take(page_size)
+        {
+            let duration = crate::music::utils::length_to_string(track.length.as_secs());
+            tracklist += &format!(
+                "{}. {} ({}) - <@{}>\n",
+                i + 1,
+                track.display_title(),
+                duration,
+                track.requester.0
+            );
+        }
+        let length = tracks.iter().map(|track| track.length).sum();
+        (tracklist, Some((page, page_count, tracks.len(), length)))
+    }
+
+    // upcoming tracks that still have to be searched for
+    pub fn unresolved(&self, limit: usize) -> Vec<QueuedTrack> {
+        self.ordered_tracks()
+            .into_iter()
+            .take(limit)
+            .filter(|track| track.lava_track.is_none() && !track.unresolvable)
+            .cloned()
+            .collect()
+    }
+
+    pub fn queued_track_mut(&mut self, id: u64) -> Option<&mut QueuedTrack> {
+        self.tracks
+            .iter_mut()
+            .chain(
+                self.user_queues
+                    .values_mut()
+                    .flat_map(|queue| queue.tracks.iter_mut()),
+            )
+            .find(|track| track.id == id)
+    }
+
+    pub fn page_tracks(&self, page: usize, page_size: usize) -> Vec<(usize, &QueuedTrack)> {
+        self.ordered_tracks()
+            .into_iter()
+            .enumerate()
+            .skip(page * page_size)
+            .take(page_size)
+            .collect()
     }
 
     pub fn user_tracklist(
         &self,
         user: UserId,
         mut page: usize,
+        page_size: usize,
     ) -> (String, Option<(usize, usize, usize, Duration)>) {
         let mut tracklist = String::new();
         let mut info = None;
         if !self.round_robin {
             tracklist += "Round robin is disabled on this server.";
         } else if let Some(queue) = self.user_queues.get(&user) {
-            let page_count = (queue.tracks.len() as f32 / 20.).ceil() as usize;
+            let page_count = (queue.tracks.len() as f32 / page_size as f32).ceil() as usize;
             if page > page_count - 1 {
                 page = page_count - 1;
             }
             let mut length = Duration::new(0, 0);
             for (i, track) in queue.tracks.iter().enumerate() {
                 length += track.length;
-                if i >= page * 20 && i < page * 20 + 20 {
-                    let title = &track.title;
+                if i >= page * page_size && i < page * page_size + page_size {
+                    let title = track.display_title();
                     let duration = crate::music::utils::length_to_string(track.length.as_secs());
                     tracklist += &format!("{}. {} ({})\n", i + 1, title, duration);
                 }
@@ -290,7 +895,7 @@ impl Queue {
         (tracklist, info)
     }
 
-    pub fn clear(&mut self, user: UserId) {
+    pub async fn clear(&mut self, user: UserId) {
         if self.round_robin && self.user_queues.remove(&user).is_some() {
             if let Ok(index) = self.users.binary_search(&user) {
                 self.users.remove(index);
@@ -298,41 +903,116 @@ impl Queue {
         } else {
             self.tracks.clear();
         }
+        self.save().await;
     }
 
     pub async fn stop(&mut self, lava: LavalinkClient) -> LavalinkResult<()> {
         self.skipped = true;
+        self.stopped = self.current_track.is_some();
         if self.round_robin {
             self.users.clear();
             self.user_queues.clear();
         } else {
             self.tracks.clear();
         }
+        self.save().await;
 
         lava.skip(self.guild_id).await;
         lava.stop(self.guild_id).await
     }
 
-    pub fn remove(&mut self, index: usize, user: UserId) -> Option<QueuedTrack> {
+    pub async fn remove_at(&mut self, index: usize) -> Option<QueuedTrack> {
+        let track = if self.round_robin {
+            let (user, index) = self.locate(index)?;
+            self.remove_user_track(user, index)
+        } else {
+            self.tracks.remove(index)
+        };
+        self.save().await;
+
+        track
+    }
+
+    pub async fn remove_multiple(&mut self, indices: &[usize]) -> Vec<QueuedTrack> {
+        let mut removed = Vec::new();
         if self.round_robin {
-            if let Some(queue) = self.user_queues.get_mut(&user) {
-                let track = queue.tracks.remove(index);
-                if queue.tracks.is_empty() {
-                    self.user_queues.remove(&user);
-                    if let Ok(index) = self.users.binary_search(&user) {
-                        self.users.remove(index);
-                    }
-                }
+            // the order changes as tracks are removed, so all of them are located first
+            let order = self.round_robin_order();
+            let mut located: Vec<(UserId, usize)> = indices
+                .iter()
+                .filter_map(|index| order.get(*index).copied())
+                .collect();
+            // removing from the back of each user's queue keeps the others valid
+            located.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+            for (user, index) in located {
+                removed.extend(self.remove_user_track(user, index));
+            }
+        } else {
+            // indices are sorted, removing from the back keeps the others valid
+            for index in indices.iter().rev() {
+                removed.extend(self.tracks.remove(*index));
+            }
+        }
+        removed.reverse();
+        self.save().await;
 
-                return track;
+        removed
+    }
+
+    pub async fn remove_user(&mut self, user: UserId) -> usize {
+        let removed = if self.round_robin {
+            match self.user_queues.get_mut(&user) {
+                Some(queue) => {
+                    let removed = queue.tracks.len();
+                    queue.tracks.clear();
+                    self.drop_empty_user_queue(user);
+                    removed
+                }
+                None => 0,
             }
-            None
         } else {
-            self.tracks.remove(index)
+            let len = self.tracks.len();
+            self.tracks.retain(|track| track.requester != user);
+            len - self.tracks.len()
+        };
+        self.save().await;
+
+        removed
+    }
+
+    fn remove_user_track(&mut self, user: UserId, index: usize) -> Option<QueuedTrack> {
+        let track = self.user_queues.get_mut(&user)?.tracks.remove(index);
+        self.drop_empty_user_queue(user);
+        track
+    }
+
+    fn drop_empty_user_queue(&mut self, user: UserId) {
+        // the queue of the user whose track is playing is cleaned up by play_next
+        let playing = self.current_track.is_some() && self.users.front() == Some(&user);
+        let is_empty = self
+            .user_queues
+            .get(&user)
+            .map_or(false, |queue| queue.tracks.is_empty());
+        if is_empty && !playing {
+            self.user_queues.remove(&user);
+            self.users.retain(|id| *id != user);
         }
     }
 
-    pub fn move_track(&mut self, from: usize, to: usize, user: UserId) -> Option<QueuedTrack> {
+    fn locate(&self, index: usize) -> Option<(UserId, usize)> {
+        self.round_robin_order().get(index).copied()
+    }
+
+    pub fn track_at(&self, index: usize) -> Option<&QueuedTrack> {
+        self.ordered_tracks().get(index).copied()
+    }
+
+    pub async fn move_track(
+        &mut self,
+        from: usize,
+        to: usize,
+        user: UserId,
+    ) -> Option<QueuedTrack> {
         let handle = if self.round_robin {
             let queue = self.user_queues.get_mut(&user)?;
             let track = queue.tracks.remove(from)?;
@@ -343,11 +1023,30 @@ impl Queue {
             self.tracks.insert(to, track.clone());
             track
         };
+        self.save().await;
 
         Some(handle)
     }
 
-    pub fn swap(
+    pub async fn move_to_top(&mut self, index: usize) -> Option<QueuedTrack> {
+        let track = if self.round_robin {
+            // with round robin the track goes to the front of its requester's queue
+            let (user, index) = self.locate(index)?;
+            let queue = self.user_queues.get_mut(&user)?;
+            let track = queue.tracks.remove(index)?;
+            queue.tracks.push_front(track.clone());
+            track
+        } else {
+            let track = self.tracks.remove(index)?;
+            self.tracks.push_front(track.clone());
+            track
+        };
+        self.save().await;
+
+        Some(track)
+    }
+
+    pub async fn swap(
         &mut self,
         first: usize,
         second: usize,
@@ -363,6 +1062,7 @@ impl Queue {
                     }
                     let handles = (queue.tracks[first].clone(), queue.tracks[second].clone());
                     queue.tracks.swap(first, second);
+                    self.save().await;
 
                     return Some(handles);
                 }
@@ -376,11 +1076,12 @@ impl Queue {
         }
         let handles = (self.tracks[first].clone(), self.tracks[second].clone());
         self.tracks.swap(first, second);
+        self.save().await;
 
         Some(handles)
     }
 
-    pub fn shuffle(&mut self, user: UserId) {
+    pub async fn shuffle(&mut self, user: UserId) {
         if self.round_robin {
             if let Some(queue) = self.user_queues.get_mut(&user) {
                 if queue.tracks.len() > 1 {
@@ -398,6 +1099,7 @@ impl Queue {
             old_tracks.shuffle(&mut rng);
             self.tracks.append(&mut old_tracks.into());
         }
+        self.save().await;
     }
 
     pub async fn skip(&mut self, lava: LavalinkClient) -> LavalinkResult<()> {
@@ -408,35 +1110,133 @@ impl Queue {
         Ok(())
     }
 
-    pub async fn play_next(&mut self, lava: LavalinkClient, http: &Http) {
+    pub async fn skip_to(
+        &mut self,
+        index: usize,
+        lava: LavalinkClient,
+    ) -> Result<Option<QueuedTrack>, crate::error::Error> {
+        let track = if self.round_robin {
+            let order = self.round_robin_order();
+            let user = match order.get(index) {
+                Some((user, _)) => *user,
+                None => return Ok(None),
+            };
+            // the tracks played before it are at the front of their users' queues
+            for (user, _) in &order[..index] {
+                if let Some(queue) = self.user_queues.get_mut(user) {
+                    queue.tracks.pop_front();
+                }
+            }
+            let mut users = self.round_robin_users();
+            self.user_queues.retain(|_, queue| !queue.tracks.is_empty());
+            users.retain(|id| self.user_queues.contains_key(id));
+            if let Some(position) = users.iter().position(|id| *id == user) {
+                users.rotate_left(position);
+            }
+            self.users = users.into();
+            if let Some(current) = self.current_track.clone() {
+                self.push_previous(current);
+            }
+            self.rearranged = true;
+            self.user_queues[&user].tracks[0].clone()
+        } else {
+            if index >= self.tracks.len() {
+                return Ok(None);
+            }
+            self.tracks.drain(..index);
+            self.tracks[0].clone()
+        };
+        self.skip(lava).await?;
+
+        Ok(Some(track))
+    }
+
+    // looks up the next track ahead of time, the queue isn't locked during the search
+    pub async fn prepare_next(queue: &Mutex<Self>, lava: &LavalinkClient) {
+        let mut queue_lock = queue.lock().await;
+        let mut track = match queue_lock.next_track_mut() {
+            Some(track) if track.lava_track.is_none() && !track.unresolvable => track.clone(),
+            // usually the resolver already found it
+            _ => return,
+        };
+        let database = queue_lock.database.clone();
+        drop(queue_lock);
+
+        if let Err(why) = track.init(lava, &database).await {
+            error!("Error preparing the next track: {}", why);
+            return;
+        }
+        let mut queue_lock = queue.lock().await;
+        // the queue could have changed in the meantime
+        if let Some(next) = queue_lock.next_track_mut() {
+            if next.id == track.id && next.lava_track.is_none() {
+                *next = track;
+            }
+        }
+    }
+
+    fn next_track_mut(&mut self) -> Option<&mut QueuedTrack> {
+        if self.round_robin {
+            match self.round_robin_order().first() {
+                Some((user, index)) => self
+                    .user_queues
+                    .get_mut(user)
+                    .and_then(|queue| queue.tracks.get_mut(*index)),
+                None => None,
+            }
+        } else {
+            self.tracks.front_mut()
+        }
+    }
+
+    // moves on from the user whose track was playing, a finished track is put back in
+    // their queue if they loop it
+    fn advance_user(&mut self, finished: Option<QueuedTrack>) {
+        let prev_user = match self.users.pop_front() {
+            Some(user) => user,
+            None => return,
+        };
+        if let Some(queue) = self.user_queues.get_mut(&prev_user) {
+            if let Some(track) = finished {
+                if queue.loop_mode == LoopModes::Song {
+                    queue.tracks.push_front(track);
+                } else if queue.loop_mode == LoopModes::Queue {
+                    queue.tracks.push_back(track);
+                }
+            }
+            if queue.tracks.is_empty() {
+                self.user_queues.remove(&prev_user);
+            } else {
+                self.users.push_back(prev_user);
+            }
+        }
+    }
+
+    pub async fn play_next(&mut self, lava: LavalinkClient) {
         //TODO: send message when there's an error playing a track
-        let mut title = None;
+        self.skip_votes.clear();
+        if self.rearranged {
+            // the queue has already been rearranged for the next track
+            self.current_track = None;
+        }
         if self.round_robin {
             let prev_track = self.current_track.take();
             if let Some(prev_track) = prev_track {
-                if let Some(prev_user) = self.users.pop_front() {
-                    if let Some(queue) = self.user_queues.get_mut(&prev_user) {
-                        if !self.skipped {
-                            if queue.loop_mode == LoopModes::Song {
-                                queue.tracks.push_front(prev_track);
-                            } else if queue.loop_mode == LoopModes::Queue {
-                                queue.tracks.push_back(prev_track);
-                            }
-                        }
-                        if queue.tracks.is_empty() {
-                            self.user_queues.remove(&prev_user);
-                        } else {
-                            self.users.push_back(prev_user);
-                        }
-                    }
-                }
+                self.push_previous(prev_track.clone());
+                let looped = (!self.skipped).then_some(prev_track);
+                self.advance_user(looped);
             }
             while let Some(next_user) = self.users.front() {
                 let queue = self.user_queues.get_mut(next_user).unwrap();
                 if let Some(mut track) = queue.tracks.pop_front() {
-                    if let Ok(lava_track) = track.init(&lava).await {
-                        title = Some(track.title.clone());
-                        if lava.play(self.guild_id, lava_track).queue().await.is_ok() {
+                    if let Ok(lava_track) = track.init(&lava, &self.database).await {
+                        if lava
+                            .play(self.guild_id, lava_track)
+                            .start_time(track.take_start_time())
+                            .queue()
+                            .await
+                            .is_ok()
+                        {
                             self.current_track = Some(track);
                             break;
                         }
@@ -447,7 +1247,7 @@ impl Queue {
                 }
             }
         } else {
-            if self.loop_mode == LoopModes::Song && !self.skipped {
+            if self.loop_mode == LoopModes::Song && !self.skipped && !self.rearranged {
                 if lava
                     .play(
                         self.guild_id,
@@ -462,12 +1262,16 @@ impl Queue {
                     .await
                     .is_ok()
                 {
+                    self.save_position(Duration::ZERO).await;
                     return;
                 }
                 error!("Error playing track!");
             }
 
             let prev_track = self.current_track.take();
+            if let Some(prev_track) = &prev_track {
+                self.push_previous(prev_track.clone());
+            }
             if self.loop_mode == LoopModes::Queue && !self.skipped {
                 if let Some(old) = prev_track {
                     self.tracks.push_back(old);
@@ -475,9 +1279,14 @@ impl Queue {
             }
 
             while let Some(mut track) = self.tracks.pop_front() {
-                if let Ok(lava_track) = track.init(&lava).await {
-                    title = Some(track.title.clone());
-                    if lava.play(self.guild_id, lava_track).queue().await.is_ok() {
+                if let Ok(lava_track) = track.init(&lava, &self.database).await {
+                    if lava
+                        .play(self.guild_id, lava_track)
+                        .start_time(track.take_start_time())
+                        .queue()
+                        .await
+                        .is_ok()
+                    {
                         self.current_track = Some(track);
                         break;
                     }
@@ -485,27 +1294,82 @@ impl Queue {
                 error!("Error playing track");
             }
         }
-        if let Some(channel) = self.channel_id {
-            if let Some(title) = title {
-                if let Err(why) = channel
-                    .send_message(http, |m| {
-                        m.embed(|e| e.title("Now playing").description(title))
-                    })
-                    .await
-                {
-                    error!("Error sending message: {:?}", why);
+        self.skipped = false;
+        self.rearranged = false;
+        self.inactivity.idle_since = match self.current_track {
+            Some(_) => None,
+            None => Some(Instant::now()),
+        };
+        self.save().await;
+        self.save_position(Duration::ZERO).await;
+    }
+
+    fn push_previous(&mut self, track: QueuedTrack) {
+        if self.previous_tracks.len() >= PREVIOUS_TRACKS {
+            self.previous_tracks.pop_front();
+        }
+        self.previous_tracks.push_back(track);
+    }
+
+    pub async fn previous(
+        &mut self,
+        lava: LavalinkClient,
+    ) -> Result<Option<QueuedTrack>, crate::error::Error> {
+        let mut track = match self.previous_tracks.pop_back() {
+            Some(track) => track,
+            None => return Ok(None),
+        };
+
+        if self.current_track.is_none() {
+            let lava_track = match track.init(&lava, &self.database).await {
+                Ok(lava_track) => lava_track,
+                Err(why) => {
+                    self.previous_tracks.push_back(track);
+                    return Err(why);
                 }
-            } else if let Err(why) = channel
-                .send_message(http, |m| m.embed(|e| e.description("The queue has ended")))
-                .await
-            {
-                error!("Error sending message: {:?}", why);
+            };
+            lava.play(self.guild_id, lava_track)
+                .start_time(track.take_start_time())
+                .queue()
+                .await?;
+            self.current_track = Some(track.clone());
+            self.inactivity.idle_since = None;
+            self.save().await;
+            return Ok(Some(track));
+        }
+
+        let current = self.current_track.clone().unwrap();
+        if self.round_robin {
+            self.push_user_front(current);
+            let user = track.requester;
+            self.push_user_front(track.clone());
+            if let Some(index) = self.users.iter().position(|id| *id == user) {
+                self.users.remove(index);
             }
+            self.users.push_front(user);
+        } else {
+            self.tracks.push_front(current);
+            self.tracks.push_front(track.clone());
         }
-        self.skipped = false;
+        self.rearranged = true;
+        self.skip(lava).await?;
+
+        Ok(Some(track))
     }
 
-    pub fn set_loop_mode(&mut self, mode: LoopModes, user: UserId) {
+    fn push_user_front(&mut self, track: QueuedTrack) {
+        let user = track.requester;
+        if let Some(queue) = self.user_queues.get_mut(&user) {
+            queue.tracks.push_front(track);
+        } else {
+            let mut queue = UserQueue::new();
+            queue.tracks.push_front(track);
+            self.user_queues.insert(user, queue);
+            self.users.push_back(user);
+        }
+    }
+
+    pub async fn set_loop_mode(&mut self, mode: LoopModes, user: UserId) {
         if self.round_robin {
             if let Some(queue) = self.user_queues.get_mut(&user) {
                 if let Some(track) = &self.current_track {
@@ -526,9 +1390,99 @@ impl Queue {
         } else {
             self.loop_mode = mode;
         }
+        self.save().await;
+    }
+
+    pub fn loop_mode(&self, user: UserId) -> LoopModes {
+        if self.round_robin {
+            self.user_queues
+                .get(&user)
+                .map_or(LoopModes::None, |queue| queue.loop_mode)
+        } else {
+            self.loop_mode
+        }
+    }
+
+    pub fn add_skip_vote(&mut self, user: UserId) -> bool {
+        self.skip_votes.insert(user)
+    }
+
+    pub fn skip_votes(&self, listeners: &[UserId]) -> usize {
+        self.skip_votes
+            .iter()
+            .filter(|user| listeners.contains(user))
+            .count()
+    }
+
+    pub fn track_count(&self) -> usize {
+        if self.round_robin {
+            self.user_queues
+                .values()
+                .map(|queue| queue.tracks.len())
+                .sum()
+        } else {
+            self.tracks.len()
+        }
+    }
+
+    pub fn user_track_count(&self, user: UserId) -> usize {
+        if self.round_robin {
+            self.user_queues
+                .get(&user)
+                .map_or(0, |queue| queue.tracks.len())
+        } else {
+            self.tracks
+                .iter()
+                .filter(|track| track.requester == user)
+                .count()
+        }
+    }
+
+    pub fn check_limits(
+        &self,
+        limits: &QueueLimits,
+        user: UserId,
+        amount: usize,
+    ) -> Result<(), crate::error::Error> {
+        if limits.max_queue_size > 0 && self.track_count() + amount > limits.max_queue_size {
+            return Err(crate::error::Error::Command(format!(
+                "The queue can have at most {} tracks on this server",
+                limits.max_queue_size
+            )));
+        }
+        if limits.max_user_tracks > 0
+            && self.user_track_count(user) + amount > limits.max_user_tracks
+        {
+            return Err(crate::error::Error::Command(format!(
+                "You can have at most {} tracks in the queue on this server",
+                limits.max_user_tracks
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn skipped(&self) -> bool {
+        self.skipped
+    }
+
+    pub fn take_stopped(&mut self) -> bool {
+        std::mem::take(&mut self.stopped)
+    }
+
+    pub fn previous_tracks(&self) -> &VecDeque<QueuedTrack> {
+        &self.previous_tracks
+    }
+
+    pub fn round_robin(&self) -> bool {
+        self.round_robin
+    }
+
+    pub fn get(&self, index: usize) -> Option<&QueuedTrack> {
+        self.tracks.get(index)
     }
 
-    pub fn set_round_robin(&mut self, round_robin: bool) {
+    pub async fn set_round_robin(&mut self, round_robin: bool) {
         if self.round_robin && !round_robin {
             self.users.clear();
             self.user_queues.clear();
@@ -536,12 +1490,114 @@ impl Queue {
             self.tracks.clear();
         }
         self.round_robin = round_robin;
+        self.save().await;
     }
 
-    pub fn clean_up(&mut self) {
+    pub async fn clean_up(&mut self) {
         self.tracks.clear();
+        self.current_track = None;
+        self.previous_tracks.clear();
+        self.voice_channel_id = None;
         self.loop_mode = LoopModes::None;
         self.users.clear();
         self.user_queues.clear();
+        self.skip_votes.clear();
+        self.inactivity = Inactivity::default();
+        self.filters = AudioFilters::default();
+        if let Some(task) = self.fade_task.take() {
+            task.abort();
+        }
+        self.segments = None;
+        self.save().await;
     }
 }
+
+struct SavedQueue {
+    guild_id: i64,
+    text_channel_id: Option<i64>,
+    voice_channel_id: Option<i64>,
+    loop_mode: i16,
+    users: Vec<i64>,
+    user_loop_modes: Vec<i16>,
+    player_channel_id: Option<i64>,
+    player_message_id: Option<i64>,
+    ordinals: Vec<i32>,
+    queries: Vec<String>,
+    titles: Vec<String>,
+    artists: Vec<String>,
+    lengths: Vec<i64>,
+    requesters: Vec<i64>,
+    lava_tracks: Vec<String>,
+    spotify_ids: Vec<String>,
+    spotify_names: Vec<String>,
+    isrcs: Vec<String>,
+}
+
+// writes the latest saved state of a queue until the queue is dropped
+async fn write_changes(database: PgPool, mut saved: watch::Receiver<Option<Arc<SavedQueue>>>) {
+    while saved.changed().await.is_ok() {
+        sleep(SAVE_DELAY).await;
+        let latest = saved.borrow_and_update().clone();
+        if let Some(latest) = latest {
+            if let Err(why) = write(&database, &latest).await {
+                error!("Error saving queue in guild {}: {}", latest.guild_id, why);
+            }
+        }
+    }
+}
+
+async fn write(database: &PgPool, saved: &SavedQueue) -> Result<(), sqlx::Error> {
+    let mut transaction = database.begin().await?;
+    sqlx::query!(
+        "INSERT INTO queues (guild_id, text_channel_id, voice_channel_id, loop_mode, users, user_loop_modes,
+                player_channel_id, player_message_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (guild_id) DO UPDATE
+                SET text_channel_id = $2,
+                    voice_channel_id = $3,
+                    loop_mode = $4,
+                    users = $5,
+                    user_loop_modes = $6,
+                    player_channel_id = $7,
+                    player_message_id = $8",
+        saved.guild_id,
+        saved.text_channel_id,
+        saved.voice_channel_id,
+        saved.loop_mode,
+        &saved.users,
+        &saved.user_loop_modes,
+        saved.player_channel_id,
+        saved.player_message_id,
+    )
+    .execute(&mut transaction)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM queued_tracks WHERE guild_id = $1",
+        saved.guild_id
+    )
+    .execute(&mut transaction)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO queued_tracks (guild_id, ordinal, query, title, artist, length, requester, lava_track,
+                spotify_id, spotify_name, isrc)
+            SELECT $1, ordinal, query, title, artist, length, requester, NULLIF(lava_track, ''),
+                NULLIF(spotify_id, ''), NULLIF(spotify_name, ''), NULLIF(isrc, '')
+            FROM UNNEST($2::integer[], $3::text[], $4::text[], $5::text[], $6::bigint[], $7::bigint[], $8::text[],
+                $9::text[], $10::text[], $11::text[])
+                AS t (ordinal, query, title, artist, length, requester, lava_track, spotify_id, spotify_name, isrc)",
+        saved.guild_id,
+        &saved.ordinals,
+        &saved.queries,
+        &saved.titles,
+        &saved.artists,
+        &saved.lengths,
+        &saved.requesters,
+        &saved.lava_tracks,
+        &saved.spotify_ids,
+        &saved.spotify_names,
+        &saved.isrcs,
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await
+}
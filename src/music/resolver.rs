@@ -0,0 +1,100 @@
+use crate::{
+    error::Error,
+    music::queue::{Queue, QueuedTrack},
+};
+use lavalink_rs::LavalinkClient;
+use serenity::prelude::Mutex;
+use sqlx::PgPool;
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Arc,
+};
+use tracing::error;
+
+// searches running at the same time for one queue
+const WORKERS: usize = 4;
+// how many upcoming tracks are resolved ahead of time
+const LOOKAHEAD: usize = 25;
+// a track is given up on after this many failed searches, even if they weren't its fault
+const MAX_FAILED_SEARCHES: u8 = 3;
+
+pub fn spawn(lava: LavalinkClient, database: PgPool, queue: Arc<Mutex<Queue>>) {
+    tokio::spawn(run(lava, database, queue));
+}
+
+async fn run(lava: LavalinkClient, database: PgPool, queue: Arc<Mutex<Queue>>) {
+    {
+        let mut queue_lock = queue.lock().await;
+        if queue_lock.resolving {
+            return;
+        }
+        queue_lock.resolving = true;
+    }
+
+    // tracks can be added while resolving, so keep going until the lookahead is done,
+    // tracks that failed are retried the next time the resolver runs
+    let mut attempted = HashSet::new();
+    loop {
+        let mut pending = queue.lock().await.unresolved(LOOKAHEAD);
+        pending.retain(|track| attempted.insert(track.id));
+        if pending.is_empty() {
+            break;
+        }
+        let pending = Arc::new(std::sync::Mutex::new(VecDeque::from(pending)));
+        let workers: Vec<_> = (0..WORKERS)
+            .map(|_| {
+                tokio::spawn(worker(
+                    lava.clone(),
+                    database.clone(),
+                    queue.clone(),
+                    pending.clone(),
+                ))
+            })
+            .collect();
+        for worker in workers {
+            if let Err(why) = worker.await {
+                error!("Error resolving tracks: {}", why);
+            }
+        }
+    }
+
+    let mut queue_lock = queue.lock().await;
+    queue_lock.resolving = false;
+    queue_lock.save().await;
+}
+
+async fn worker(
+    lava: LavalinkClient,
+    database: PgPool,
+    queue: Arc<Mutex<Queue>>,
+    pending: Arc<std::sync::Mutex<VecDeque<QueuedTrack>>>,
+) {
+    loop {
+        let next = pending.lock().unwrap().pop_front();
+        let mut track = match next {
+            Some(track) => track,
+            None => return,
+        };
+        let result = track.init(&lava, &database).await;
+
+        let mut queue_lock = queue.lock().await;
+        // the track could have been removed or started playing in the meantime
+        let queued = match queue_lock.queued_track_mut(track.id) {
+            Some(queued) => queued,
+            None => continue,
+        };
+        match result {
+            Ok(_) => *queued = track,
+            // nothing was found
+            Err(Error::Command(why)) => {
+                error!("Error resolving {}: {}", track.query, why);
+                queued.unresolvable = true;
+            }
+            Err(why) => {
+                error!("Error resolving {}: {}", track.query, why);
+                queued.failed_searches += 1;
+                queued.unresolvable = queued.failed_searches >= MAX_FAILED_SEARCHES;
+            }
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use crate::music::queue::QueuedTrack;
+use serenity::model::id::UserId;
+use sqlx::PgPool;
+use std::time::Duration;
+
+pub struct SavedPlaylist {
+    pub name: String,
+    pub shared: bool,
+    pub track_count: usize,
+}
+
+pub async fn save(
+    database: &PgPool,
+    owner: UserId,
+    name: &str,
+    tracks: &[QueuedTrack],
+) -> Result<(), sqlx::Error> {
+    let mut ordinals = Vec::new();
+    let mut queries = Vec::new();
+    let mut titles = Vec::new();
+    let mut artists = Vec::new();
+    let mut lengths = Vec::new();
+    let mut lava_tracks = Vec::new();
+    for (ordinal, track) in tracks.iter().enumerate() {
+        ordinals.push(ordinal as i32);
+        queries.push(track.query.clone());
+        titles.push(track.title.clone());
+        artists.push(track.artist.clone());
+        lengths.push(track.length.as_millis() as i64);
+        lava_tracks.push(track.saved_lava_track());
+    }
+
+    let mut transaction = database.begin().await?;
+    let playlist = sqlx::query!(
+        "INSERT INTO saved_playlists (owner, name)
+        VALUES ($1, $2)
+        ON CONFLICT (owner, name) DO UPDATE SET name = $2
+        RETURNING id",
+        owner.0 as i64,
+        name
+    )
+    .fetch_one(&mut transaction)
+    .await?;
+    sqlx::query!(
+        "DELETE FROM saved_playlist_tracks WHERE playlist_id = $1",
+        playlist.id
+    )
+    .execute(&mut transaction)
+    .await?;
+    sqlx::query!(
+        "INSERT INTO saved_playlist_tracks (playlist_id, ordinal, query, title, artist, length, lava_track)
+        SELECT $1, ordinal, query, title, artist, length, NULLIF(lava_track, '')
+        FROM UNNEST($2::integer[], $3::text[], $4::text[], $5::text[], $6::bigint[], $7::text[])
+            AS t (ordinal, query, title, artist, length, lava_track)",
+        playlist.id,
+        &ordinals,
+        &queries,
+        &titles,
+        &artists,
+        &lengths,
+        &lava_tracks,
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await
+}
+
+pub async fn load(
+    database: &PgPool,
+    owner: UserId,
+    name: &str,
+    requester: UserId,
+) -> Result<Option<Vec<QueuedTrack>>, sqlx::Error> {
+    let playlist = match sqlx::query!(
+        "SELECT id, shared FROM saved_playlists WHERE owner = $1 AND name = $2",
+        owner.0 as i64,
+        name
+    )
+    .fetch_optional(database)
+    .await?
+    {
+        Some(playlist) if playlist.shared || owner == requester => playlist,
+        _ => return Ok(None),
+    };
+
+    let rows = sqlx::query!(
+        "SELECT query, title, artist, length, lava_track
+        FROM saved_playlist_tracks WHERE playlist_id = $1 ORDER BY ordinal",
+        playlist.id
+    )
+    .fetch_all(database)
+    .await?;
+
+    Ok(Some(
+        rows.into_iter()
+            .map(|row| {
+                QueuedTrack::from_saved(
+                    row.query,
+                    row.title,
+                    row.artist,
+                    Duration::from_millis(row.length as u64),
+                    requester,
+                    row.lava_track,
+                )
+            })
+            .collect(),
+    ))
+}
+
+pub async fn list(database: &PgPool, owner: UserId) -> Result<Vec<SavedPlaylist>, sqlx::Error> {
+    let rows = sqlx::query!(
+        "SELECT p.name, p.shared, count(t.ordinal) AS track_count
+        FROM saved_playlists p
+        LEFT JOIN saved_playlist_tracks t ON t.playlist_id = p.id
+        WHERE p.owner = $1
+        GROUP BY p.id
+        ORDER BY p.name",
+        owner.0 as i64
+    )
+    .fetch_all(database)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| SavedPlaylist {
+            name: row.name,
+            shared: row.shared,
+            track_count: row.track_count.unwrap_or(0) as usize,
+        })
+        .collect())
+}
+
+pub async fn delete(database: &PgPool, owner: UserId, name: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM saved_playlists WHERE owner = $1 AND name = $2",
+        owner.0 as i64,
+        name
+    )
+    .execute(database)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn set_shared(
+    database: &PgPool,
+    owner: UserId,
+    name: &str,
+    shared: bool,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "UPDATE saved_playlists SET shared = $3 WHERE owner = $1 AND name = $2",
+        owner.0 as i64,
+        name,
+        shared
+    )
+    .execute(database)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,142 @@
+use crate::error::Error;
+use reqwest::{Client, StatusCode};
+use serde_derive::Deserialize;
+use std::time::Duration;
+
+const CATEGORIES: &str = r#"["sponsor","selfpromo","interaction","intro","outro","chapter"]"#;
+const ACTION_TYPES: &str = r#"["skip","chapter"]"#;
+// segments ending this close to the position aren't worth seeking for
+const MIN_SKIP: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Segment {
+    segment: (f64, f64),
+    category: String,
+    action_type: String,
+    #[serde(default)]
+    description: String,
+}
+
+pub struct Chapter {
+    pub title: String,
+    pub start: Duration,
+    pub end: Duration,
+}
+
+#[derive(Default)]
+pub struct Segments {
+    skips: Vec<(Duration, Duration)>,
+    pub chapters: Vec<Chapter>,
+}
+impl Segments {
+    // where to seek to if the position is inside a segment, each segment is only skipped once
+    pub fn skip_from(&mut self, position: Duration) -> Option<Duration> {
+        let index = self
+            .skips
+            .iter()
+            .position(|(start, end)| *start <= position && position + MIN_SKIP < *end)?;
+        Some(self.skips.remove(index).1)
+    }
+
+    pub fn chapter_at(&self, position: Duration) -> Option<usize> {
+        self.chapters
+            .iter()
+            .rposition(|chapter| chapter.start <= position && position < chapter.end)
+    }
+}
+
+#[derive(Clone)]
+pub struct SponsorBlock {
+    client: Client,
+    url: String,
+}
+impl SponsorBlock {
+    pub fn new(url: &str) -> Self {
+        SponsorBlock {
+            client: Client::new(),
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub async fn segments(&self, video_id: &str) -> Result<Segments, Error> {
+        let response = self
+            .client
+            .get(format!("{}/api/skipSegments", self.url))
+            .query(&[
+                ("videoID", video_id),
+                ("categories", CATEGORIES),
+                ("actionTypes", ACTION_TYPES),
+            ])
+            .send()
+            .await?;
+        // videos without any segments are a 404
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(Segments::default());
+        }
+        let body = response.error_for_status()?.text().await?;
+        parse(&body)
+    }
+}
+
+// the server is configurable, so segments with times that don't make sense are dropped
+fn parse(body: &str) -> Result<Segments, Error> {
+    let segments: Vec<Segment> = serde_json::from_str(body)
+        .map_err(|why| Error::Command(format!("Invalid SponsorBlock response: {why}")))?;
+
+    let mut result = Segments::default();
+    for segment in segments {
+        let (start, end) = segment.segment;
+        let (start, end) = match (
+            Duration::try_from_secs_f64(start.max(0.0)),
+            Duration::try_from_secs_f64(end.max(0.0)),
+        ) {
+            (Ok(start), Ok(end)) if start < end => (start, end),
+            _ => continue,
+        };
+        if segment.action_type == "chapter" || segment.category == "chapter" {
+            result.chapters.push(Chapter {
+                title: segment.description,
+                start,
+                end,
+            });
+        } else if segment.action_type == "skip" {
+            result.skips.push((start, end));
+        }
+    }
+    result.skips.sort_by_key(|(start, _)| *start);
+    result.chapters.sort_by_key(|chapter| chapter.start);
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_segments() {
+        let body = r#"[
+            {"segment": [10.0, 20.5], "category": "sponsor", "actionType": "skip"},
+            {"segment": [-5.0, 3.0], "category": "intro", "actionType": "skip"},
+            {"segment": [30.0, 1e300], "category": "outro", "actionType": "skip"},
+            {"segment": [1e300, 1e301], "category": "sponsor", "actionType": "skip"},
+            {"segment": [40.0, 40.0], "category": "sponsor", "actionType": "skip"},
+            {"segment": [60.0, 50.0], "category": "chapter", "actionType": "chapter", "description": "Backwards"},
+            {"segment": [0.0, 60.0], "category": "chapter", "actionType": "chapter", "description": "Intro"}
+        ]"#;
+        let segments = parse(body).unwrap();
+        assert_eq!(
+            segments.skips,
+            vec![
+                (Duration::ZERO, Duration::from_secs(3)),
+                (Duration::from_secs(10), Duration::from_secs_f64(20.5)),
+            ]
+        );
+        assert_eq!(segments.chapters.len(), 1);
+        assert_eq!(segments.chapters[0].title, "Intro");
+        assert_eq!(segments.chapters[0].end, Duration::from_secs(60));
+
+        assert!(parse("not json").is_err());
+    }
+}
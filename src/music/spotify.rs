@@ -0,0 +1,407 @@
+use crate::{
+    error::{Error, SpotifyError},
+    music::queue::{QueuedTrack, SpotifyInfo},
+};
+use regex::Regex;
+use reqwest::{redirect::Policy, Client, Url};
+use rspotify::{
+    clients::BaseClient,
+    model::{
+        AlbumId, ArtistId, Country, EpisodeId, FullTrack, Id, Market, PlayableItem, PlaylistId,
+        ShowId, SimplifiedArtist, SimplifiedTrack, TrackId,
+    },
+    ClientCredsSpotify, ClientResult,
+};
+use serenity::model::id::UserId;
+use std::time::Duration;
+
+const URL: &str = r"^(?:https?://)?(?:open|play)\.spotify\.com/(?:intl-[a-zA-Z_-]+/)?(?:embed/)?(?:user/[^/]+/)?([a-z]+)/([a-zA-Z0-9]+)";
+const URI: &str = r"^spotify:(?:user:[^:]+:)?([a-z]+):([a-zA-Z0-9]+)$";
+const ALBUM_LIMIT: u32 = 50;
+const PLAYLIST_LIMIT: u32 = 100;
+const SHOW_LIMIT: u32 = 50;
+const TRACKS_LIMIT: usize = 50;
+const SHORTLINK_HOST: &str = "spotify.link";
+// the hosts a shortlink can redirect through
+const REDIRECT_HOSTS: [&str; 3] = [SHORTLINK_HOST, "spotify.app.link", "open.spotify.com"];
+const MAX_REDIRECTS: usize = 5;
+const MAX_SHORTLINK_BODY: usize = 512 * 1024;
+const SHORTLINK_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SpotifyLink {
+    Track(String),
+    Album(String),
+    Playlist(String),
+    Artist(String),
+    Show(String),
+    Episode(String),
+}
+impl SpotifyLink {
+    fn new(kind: &str, id: &str) -> Result<Self, SpotifyError> {
+        let id = id.to_string();
+        match kind {
+            "track" => Ok(SpotifyLink::Track(id)),
+            "album" => Ok(SpotifyLink::Album(id)),
+            "playlist" => Ok(SpotifyLink::Playlist(id)),
+            "artist" => Ok(SpotifyLink::Artist(id)),
+            "show" => Ok(SpotifyLink::Show(id)),
+            "episode" => Ok(SpotifyLink::Episode(id)),
+            _ => Err(SpotifyError::UnsupportedLink(kind.to_string())),
+        }
+    }
+}
+
+pub fn is_link(query: &str) -> bool {
+    let query = query.trim();
+    query.starts_with("spotify:")
+        || query.contains("open.spotify.com/")
+        || query.contains("play.spotify.com/")
+        || query.contains("spotify.link/")
+}
+
+// accepts spotify: uris, open.spotify.com links (with or without an intl-xx part) and spotify.link shortlinks
+pub async fn parse(query: &str) -> Result<SpotifyLink, SpotifyError> {
+    let query = query.trim();
+    if query.contains("spotify.link/") {
+        return resolve_shortlink(query).await;
+    }
+    parse_link(query)
+}
+
+fn parse_link(link: &str) -> Result<SpotifyLink, SpotifyError> {
+    let pattern = if link.starts_with("spotify:") {
+        URI
+    } else {
+        URL
+    };
+    let captures = Regex::new(pattern)
+        .unwrap()
+        .captures(link)
+        .ok_or_else(|| SpotifyError::InvalidLink(link.to_string()))?;
+    SpotifyLink::new(&captures[1], &captures[2])
+}
+
+// shortlinks redirect to the full link, or to a page containing it
+async fn resolve_shortlink(link: &str) -> Result<SpotifyLink, SpotifyError> {
+    let error = || SpotifyError::Shortlink(link.to_string());
+    let url = if link.contains("://") {
+        Url::parse(link)
+    } else {
+        Url::parse(&format!("https://{link}"))
+    }
+    .map_err(|_| error())?;
+    if !is_https(&url) || url.host_str() != Some(SHORTLINK_HOST) {
+        return Err(error());
+    }
+
+    // only redirects between spotify's own hosts are followed
+    let client = Client::builder()
+        .redirect(Policy::custom(|attempt| {
+            let allowed = is_https(attempt.url())
+                && REDIRECT_HOSTS.contains(&attempt.url().host_str().unwrap_or_default());
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if allowed {
+                attempt.follow()
+            } else {
+                attempt.stop()
+            }
+        }))
+        .timeout(SHORTLINK_TIMEOUT)
+        .build()
+        .map_err(|_| error())?;
+    let mut response = client.get(url).send().await.map_err(|_| error())?;
+    if let Ok(resolved) = parse_link(response.url().as_str()) {
+        return Ok(resolved);
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|_| error())? {
+        body.extend_from_slice(&chunk);
+        if body.len() > MAX_SHORTLINK_BODY {
+            return Err(error());
+        }
+    }
+    let body = String::from_utf8_lossy(&body);
+    let full_link = Regex::new(r"https://open\.spotify\.com/[^\s\x22'<>]+").unwrap();
+    full_link
+        .find_iter(&body)
+        .find_map(|found| parse_link(found.as_str()).ok())
+        .ok_or_else(error)
+}
+
+fn is_https(url: &Url) -> bool {
+    url.scheme() == "https" && url.port().is_none()
+}
+
+// the search query for a single track or episode
+pub async fn track(
+    spotify: &ClientCredsSpotify,
+    link: &SpotifyLink,
+    requester: UserId,
+) -> Result<QueuedTrack, Error> {
+    match link {
+        SpotifyLink::Track(id) => {
+            let id = TrackId::from_id(id)?;
+            let track = spotify.track(id).await?;
+            let track = from_full_track(&track, requester).ok_or("This track has no artist")?;
+            Ok(track)
+        }
+        SpotifyLink::Episode(id) => {
+            let id = EpisodeId::from_id(id)?;
+            let episode = spotify.get_an_episode(id, None).await?;
+            Ok(queued_episode(
+                &episode.show.name,
+                &episode.name,
+                &episode.id,
+                episode.duration,
+                requester,
+            ))
+        }
+        _ => Err(
+            "Use the `playlist` command to queue an album, a playlist, an artist or a show".into(),
+        ),
+    }
+}
+
+pub async fn tracks(
+    spotify: &ClientCredsSpotify,
+    link: &SpotifyLink,
+    requester: UserId,
+) -> Result<Vec<QueuedTrack>, Error> {
+    let mut tracks = Vec::new();
+    let mut offset = 0;
+    match link {
+        SpotifyLink::Album(id) => {
+            let id = AlbumId::from_id(id)?;
+            loop {
+                let album = spotify
+                    .album_track_manual(id.clone(), Some(ALBUM_LIMIT), Some(offset))
+                    .await?;
+
+                tracks.extend(from_simplified_tracks(spotify, &album.items, requester).await?);
+
+                if album.next.is_none() {
+                    break;
+                }
+                offset += ALBUM_LIMIT;
+            }
+        }
+        SpotifyLink::Playlist(id) => {
+            let id = PlaylistId::from_id(id)?;
+            loop {
+                let playlist = spotify
+                    .playlist_items_manual(
+                        id.clone(),
+                        None,
+                        None,
+                        Some(PLAYLIST_LIMIT),
+                        Some(offset),
+                    )
+                    .await?;
+
+                for item in playlist.items {
+                    match item.track {
+                        Some(PlayableItem::Track(track)) => {
+                            tracks.extend(from_full_track(&track, requester))
+                        }
+                        Some(PlayableItem::Episode(episode)) => tracks.push(queued_episode(
+                            &episode.show.name,
+                            &episode.name,
+                            &episode.id,
+                            episode.duration,
+                            requester,
+                        )),
+                        None => {}
+                    }
+                }
+
+                if playlist.next.is_none() {
+                    break;
+                }
+                offset += PLAYLIST_LIMIT;
+            }
+        }
+        SpotifyLink::Artist(id) => {
+            let id = ArtistId::from_id(id)?;
+            let top_tracks = spotify
+                .artist_top_tracks(id, &Market::Country(Country::UnitedStates))
+                .await?;
+            for track in top_tracks {
+                tracks.extend(from_full_track(&track, requester));
+            }
+        }
+        SpotifyLink::Show(id) => {
+            let id = ShowId::from_id(id)?;
+            let show = spotify.get_a_show(id.clone(), None).await?;
+            loop {
+                let episodes = spotify
+                    .get_shows_episodes_manual(id.clone(), None, Some(SHOW_LIMIT), Some(offset))
+                    .await?;
+
+                for episode in episodes.items {
+                    tracks.push(queued_episode(
+                        &show.name,
+                        &episode.name,
+                        &episode.id,
+                        episode.duration,
+                        requester,
+                    ));
+                }
+
+                if episodes.next.is_none() {
+                    break;
+                }
+                offset += SHOW_LIMIT;
+            }
+        }
+        SpotifyLink::Track(_) | SpotifyLink::Episode(_) => {
+            return Err("Use the `play` command to queue a single track".into())
+        }
+    }
+
+    Ok(tracks)
+}
+
+fn from_full_track(track: &FullTrack, requester: UserId) -> Option<QueuedTrack> {
+    let mut queued = queued_track(&track.name, &track.artists, track.duration, requester)?;
+    queued.spotify = track.id.as_ref().map(|id| SpotifyInfo {
+        id: id.id().to_string(),
+        name: track.name.clone(),
+        isrc: track.external_ids.get("isrc").cloned(),
+    });
+    Some(queued)
+}
+
+// simplified tracks have no isrc to match with, so the full tracks are fetched
+pub async fn from_simplified_tracks(
+    spotify: &ClientCredsSpotify,
+    tracks: &[SimplifiedTrack],
+    requester: UserId,
+) -> ClientResult<Vec<QueuedTrack>> {
+    let ids: Vec<TrackId> = tracks.iter().filter_map(|track| track.id.clone()).collect();
+    let mut queued = Vec::new();
+    for chunk in ids.chunks(TRACKS_LIMIT) {
+        let full_tracks = spotify.tracks(chunk.iter(), None).await?;
+        queued.extend(
+            full_tracks
+                .iter()
+                .filter_map(|track| from_full_track(track, requester)),
+        );
+    }
+    Ok(queued)
+}
+
+fn queued_track(
+    name: &str,
+    artists: &[SimplifiedArtist],
+    length: Duration,
+    requester: UserId,
+) -> Option<QueuedTrack> {
+    let artist = artists.first()?.name.clone();
+    let query = format!("{} - {}", &artist, name);
+    Some(QueuedTrack::new(query, artist, length, requester))
+}
+
+fn queued_episode(
+    show: &str,
+    name: &str,
+    id: &EpisodeId,
+    length: Duration,
+    requester: UserId,
+) -> QueuedTrack {
+    let query = format!("{} - {}", show, name);
+    let mut queued = QueuedTrack::new(query, show.to_string(), length, requester);
+    queued.spotify = Some(SpotifyInfo {
+        id: id.id().to_string(),
+        name: name.to_string(),
+        isrc: None,
+    });
+    queued
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "4uLU6hMCjMI75M1A2tKUQC";
+
+    #[test]
+    fn links() {
+        let track = Some(SpotifyLink::Track(ID.to_string()));
+        for link in [
+            format!("spotify:track:{ID}"),
+            format!("https://open.spotify.com/track/{ID}"),
+            format!("https://open.spotify.com/track/{ID}?si=abc123"),
+            format!("https://open.spotify.com/intl-de/track/{ID}"),
+            format!("https://open.spotify.com/intl-pt_BR/track/{ID}"),
+            format!("https://open.spotify.com/embed/track/{ID}"),
+            format!("https://play.spotify.com/track/{ID}"),
+            format!("open.spotify.com/track/{ID}"),
+        ] {
+            assert_eq!(parse_link(&link).ok(), track, "{link}");
+        }
+
+        let parse = |link: String| parse_link(&link).ok();
+        assert_eq!(
+            parse(format!("spotify:user:someone:playlist:{ID}")),
+            Some(SpotifyLink::Playlist(ID.to_string()))
+        );
+        assert_eq!(
+            parse(format!(
+                "https://open.spotify.com/user/someone/playlist/{ID}"
+            )),
+            Some(SpotifyLink::Playlist(ID.to_string()))
+        );
+        assert_eq!(
+            parse(format!("https://open.spotify.com/album/{ID}")),
+            Some(SpotifyLink::Album(ID.to_string()))
+        );
+        assert_eq!(
+            parse(format!("spotify:artist:{ID}")),
+            Some(SpotifyLink::Artist(ID.to_string()))
+        );
+        assert_eq!(
+            parse(format!("https://open.spotify.com/episode/{ID}")),
+            Some(SpotifyLink::Episode(ID.to_string()))
+        );
+        assert_eq!(
+            parse(format!("spotify:show:{ID}")),
+            Some(SpotifyLink::Show(ID.to_string()))
+        );
+        assert_eq!(
+            parse(format!("https://open.spotify.com/show/{ID}")),
+            Some(SpotifyLink::Show(ID.to_string()))
+        );
+    }
+
+    #[test]
+    fn invalid_links() {
+        for link in [
+            "".to_string(),
+            "spotify:track:".to_string(),
+            format!("spotify:track:{ID}:extra"),
+            format!("spotify:audiobook:{ID}"),
+            format!("https://open.spotify.com/concert/{ID}"),
+            format!("https://example.com/track/{ID}"),
+            format!("https://evil.com/?open.spotify.com/track/{ID}"),
+        ] {
+            assert!(parse_link(&link).is_err(), "{link}");
+        }
+    }
+
+    #[tokio::test]
+    async fn shortlink_hosts() {
+        for link in [
+            "http://spotify.link/abc",
+            "https://spotify.link:8443/abc",
+            "https://evil.com/spotify.link/abc",
+            "https://spotify.link.evil.com/abc",
+            "https://spotify.link@127.0.0.1/abc",
+            "file:///spotify.link/abc",
+        ] {
+            assert!(resolve_shortlink(link).await.is_err(), "{link}");
+        }
+    }
+}
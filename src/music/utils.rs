@@ -1,64 +1,432 @@
 use crate::{
     error::Error::{self, Join},
-    music::queue::Queue,
+    music::{
+        queue::{DuplicatePolicy, Placement, Queue, QueueLimits, QueuedTrack},
+        resolver,
+    },
+    shared_data::{Data, Guilds},
     Context,
 };
 use lavalink_rs::LavalinkClient;
 use serenity::{
-    model::id::{ChannelId, GuildId},
+    model::{
+        application::interaction::message_component::MessageComponentInteraction,
+        guild::Guild,
+        id::{ChannelId, GuildId, UserId},
+    },
     prelude::Mutex,
 };
-use std::sync::Arc;
+use sqlx::PgPool;
+use std::{
+    ops::RangeInclusive,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::{error, info};
 
-pub async fn voice_check(
-    ctx: &Context<'_>,
-    should_join: bool,
-) -> Result<(LavalinkClient, Arc<Mutex<Queue>>), Error> {
-    let guild = ctx.guild().unwrap();
-    let guild_id = guild.id;
+// who triggered a command or a button, so both go through the same checks
+pub struct Caller<'a> {
+    pub ctx: &'a serenity::prelude::Context,
+    pub data: &'a Data,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub user_id: UserId,
+}
+impl<'a> Caller<'a> {
+    pub fn from_command(ctx: &Context<'a>) -> Option<Self> {
+        Some(Caller {
+            ctx: ctx.serenity_context(),
+            data: ctx.data(),
+            guild_id: ctx.guild_id()?,
+            channel_id: ctx.channel_id(),
+            user_id: ctx.author().id,
+        })
+    }
 
-    let user_channel_id = guild
-        .voice_states
-        .get(&ctx.author().id)
-        .and_then(|voice_state| voice_state.channel_id);
-
-    if let Some(user_channel_id) = user_channel_id {
-        let bot_channel_id = guild
-            .voice_states
-            .get(&ctx.serenity_context().cache.current_user_id())
-            .and_then(|voice_state| voice_state.channel_id);
-
-        if let Some(bot_channel_id) = bot_channel_id {
-            if bot_channel_id == user_channel_id {
-                let data = ctx.data();
-                let lava = data.lavalink.clone();
-                let queue = data.guilds.get_queue(guild_id).await;
-                Ok((lava, queue))
-            } else {
-                Err(Join(
-                    "You must be in the same voice channel to use this command".to_string(),
+    pub fn from_component(
+        ctx: &'a serenity::prelude::Context,
+        data: &'a Data,
+        mci: &MessageComponentInteraction,
+    ) -> Option<Self> {
+        Some(Caller {
+            ctx,
+            data,
+            guild_id: mci.guild_id?,
+            channel_id: mci.channel_id,
+            user_id: mci.user.id,
+        })
+    }
+
+    fn voice_channels(&self) -> (Option<ChannelId>, Option<ChannelId>) {
+        let guild = match self.ctx.cache.guild(self.guild_id) {
+            Some(guild) => guild,
+            None => return (None, None),
+        };
+        let channel_of = |user_id| {
+            guild
+                .voice_states
+                .get(&user_id)
+                .and_then(|voice_state| voice_state.channel_id)
+        };
+
+        (
+            channel_of(self.user_id),
+            channel_of(self.ctx.cache.current_user_id()),
+        )
+    }
+
+    pub async fn voice_check(&self) -> Result<(LavalinkClient, Arc<Mutex<Queue>>), Error> {
+        let (user_channel_id, bot_channel_id) = self.voice_channels();
+        let user_channel_id = match user_channel_id {
+            Some(user_channel_id) => user_channel_id,
+            None => {
+                return Err(Join(
+                    "You must in a voice channel to use this command.".to_string(),
                 ))
             }
-        } else {
-            if should_join {
-                return join(ctx, guild_id, user_channel_id, ctx.channel_id()).await;
+        };
+        self.voice_channel_check(user_channel_id).await?;
+
+        match bot_channel_id {
+            Some(bot_channel_id) if bot_channel_id == user_channel_id => {
+                let lava = self.data.lavalink.clone();
+                let queue = self.data.guilds.get_queue(self.guild_id).await?;
+                Ok((lava, queue))
             }
-            Err(Join("Not in a voice channel".to_string()))
+            Some(_) => Err(Join(
+                "You must be in the same voice channel to use this command".to_string(),
+            )),
+            None => Err(Join("Not in a voice channel".to_string())),
+        }
+    }
+
+    pub async fn voice_channel_check(&self, channel_id: ChannelId) -> Result<(), Error> {
+        let guild = self.data.guilds.get(self.guild_id).await?;
+        let voice_channel_id = guild.lock().await.voice_channel_id;
+
+        match voice_channel_id {
+            Some(voice_channel_id) if voice_channel_id != channel_id => Err(Join(format!(
+                "Music can only be played in <#{voice_channel_id}>"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn is_dj(&self) -> Result<bool, Error> {
+        let guild = self.data.guilds.get(self.guild_id).await?;
+        let no_dj_roles = guild.lock().await.dj_role_ids.is_empty();
+
+        Ok(no_dj_roles || self.has_dj_role().await? || self.is_alone())
+    }
+
+    pub async fn has_dj_role(&self) -> Result<bool, Error> {
+        let guild = self.data.guilds.get(self.guild_id).await?;
+        let dj_role_ids = guild.lock().await.dj_role_ids.clone();
+        if dj_role_ids.is_empty() {
+            return Ok(false);
+        }
+
+        Ok(self
+            .guild_id
+            .member(self.ctx, self.user_id)
+            .await
+            .map_or(false, |member| {
+                member.roles.iter().any(|role| dj_role_ids.contains(role))
+            }))
+    }
+
+    pub fn is_alone(&self) -> bool {
+        self.bot_listeners() == [self.user_id]
+    }
+
+    pub async fn queue_limits(&self) -> Result<QueueLimits, Error> {
+        let guild = self.data.guilds.get(self.guild_id).await?;
+        let limits = guild.lock().await.limits;
+        Ok(limits)
+    }
+
+    pub async fn duplicate_policy(&self) -> Result<DuplicatePolicy, Error> {
+        let guild = self.data.guilds.get(self.guild_id).await?;
+        let duplicate_policy = guild.lock().await.duplicate_policy;
+        Ok(duplicate_policy)
+    }
+
+    pub async fn enqueue(
+        &self,
+        queue: &Arc<Mutex<Queue>>,
+        track: QueuedTrack,
+        lava: LavalinkClient,
+    ) -> Result<bool, Error> {
+        self.enqueue_at(queue, track, Placement::Back, lava).await
+    }
+
+    pub async fn enqueue_at(
+        &self,
+        queue: &Arc<Mutex<Queue>>,
+        track: QueuedTrack,
+        placement: Placement,
+        lava: LavalinkClient,
+    ) -> Result<bool, Error> {
+        let limits = self.queue_limits().await?;
+        limits.check_length(track.length)?;
+        let duplicate_policy = self.duplicate_policy().await?;
+
+        let mut queue_lock = queue.lock().await;
+        queue_lock.check_limits(&limits, track.requester, 1)?;
+        let duplicate = duplicate_policy != DuplicatePolicy::Allow && queue_lock.contains(&track);
+        if duplicate && duplicate_policy == DuplicatePolicy::Reject {
+            return Err("This track is already in the queue".into());
+        }
+        queue_lock.enqueue_at(track, placement, lava).await?;
+
+        Ok(duplicate)
+    }
+
+    pub async fn enqueue_multiple(
+        &self,
+        queue: &Arc<Mutex<Queue>>,
+        mut tracks: Vec<QueuedTrack>,
+        lava: LavalinkClient,
+    ) -> Result<String, Error> {
+        let limits = self.queue_limits().await?;
+        limits.check_playlist_size(tracks.len())?;
+        let total = tracks.len();
+        tracks.retain(|track| limits.allows_length(track.length));
+        let too_long = total - tracks.len();
+        if tracks.is_empty() {
+            return Err(Error::Command(format!(
+                "All tracks are longer than {}",
+                length_to_string(limits.max_track_length.as_secs())
+            )));
+        }
+
+        let skip_duplicates = self.duplicate_policy().await? != DuplicatePolicy::Allow;
+        let mut queue_lock = queue.lock().await;
+        queue_lock.check_limits(&limits, self.user_id, tracks.len())?;
+        let amount = tracks.len();
+        let duplicates = match queue_lock
+            .enqueue_multiple(tracks, lava, skip_duplicates)
+            .await
+        {
+            Ok(duplicates) => duplicates,
+            Err(_) => return Err("Error queuing the tracks".into()),
+        };
+        drop(queue_lock);
+        resolver::spawn(
+            self.data.lavalink.clone(),
+            self.data.database.clone(),
+            queue.clone(),
+        );
+
+        let mut description = format!("Added {} tracks to the queue", amount - duplicates);
+        if duplicates > 0 {
+            description += &format!("\n{} duplicate tracks were skipped", duplicates);
+        }
+        if too_long > 0 {
+            description += &format!(
+                "\n{} tracks longer than {} were skipped",
+                too_long,
+                length_to_string(limits.max_track_length.as_secs())
+            );
+        }
+
+        Ok(description)
+    }
+
+    pub fn bot_listeners(&self) -> Vec<UserId> {
+        let guild = match self.ctx.cache.guild(self.guild_id) {
+            Some(guild) => guild,
+            None => return Vec::new(),
+        };
+
+        match self.voice_channels().1 {
+            Some(channel_id) => listeners(self.ctx, &guild, channel_id),
+            None => Vec::new(),
         }
+    }
+}
+
+pub async fn voice_check(
+    ctx: &Context<'_>,
+    should_join: bool,
+) -> Result<(LavalinkClient, Arc<Mutex<Queue>>), Error> {
+    let caller = Caller::from_command(ctx).unwrap();
+    if should_join {
+        if let (Some(user_channel_id), None) = caller.voice_channels() {
+            caller.voice_channel_check(user_channel_id).await?;
+            return join(ctx, caller.guild_id, user_channel_id, ctx.channel_id()).await;
+        }
+    }
+
+    caller.voice_check().await
+}
+
+pub async fn queue_limits(ctx: &Context<'_>) -> Result<QueueLimits, Error> {
+    Caller::from_command(ctx).unwrap().queue_limits().await
+}
+
+pub async fn duplicate_policy(ctx: &Context<'_>) -> Result<DuplicatePolicy, Error> {
+    Caller::from_command(ctx).unwrap().duplicate_policy().await
+}
+
+pub async fn enqueue(
+    ctx: &Context<'_>,
+    queue: &Arc<Mutex<Queue>>,
+    track: QueuedTrack,
+    lava: LavalinkClient,
+) -> Result<bool, Error> {
+    Caller::from_command(ctx)
+        .unwrap()
+        .enqueue(queue, track, lava)
+        .await
+}
+
+pub async fn enqueue_at(
+    ctx: &Context<'_>,
+    queue: &Arc<Mutex<Queue>>,
+    track: QueuedTrack,
+    placement: Placement,
+    lava: LavalinkClient,
+) -> Result<bool, Error> {
+    Caller::from_command(ctx)
+        .unwrap()
+        .enqueue_at(queue, track, placement, lava)
+        .await
+}
+
+pub async fn enqueue_multiple(
+    ctx: &Context<'_>,
+    queue: &Arc<Mutex<Queue>>,
+    tracks: Vec<QueuedTrack>,
+    lava: LavalinkClient,
+) -> Result<String, Error> {
+    Caller::from_command(ctx)
+        .unwrap()
+        .enqueue_multiple(queue, tracks, lava)
+        .await
+}
+
+pub async fn voice_channel_check(ctx: &Context<'_>, channel_id: ChannelId) -> Result<(), Error> {
+    Caller::from_command(ctx)
+        .unwrap()
+        .voice_channel_check(channel_id)
+        .await
+}
+
+pub async fn is_dj(ctx: &Context<'_>) -> Result<bool, Error> {
+    match Caller::from_command(ctx) {
+        Some(caller) => caller.is_dj().await,
+        None => Ok(false),
+    }
+}
+
+pub fn bot_listeners(ctx: &Context<'_>) -> Vec<UserId> {
+    Caller::from_command(ctx).map_or(Vec::new(), |caller| caller.bot_listeners())
+}
+
+pub async fn skip(
+    caller: &Caller<'_>,
+    lava: LavalinkClient,
+    queue: &Arc<Mutex<Queue>>,
+) -> Result<String, Error> {
+    let (vote_skip, threshold) = {
+        let guild = caller.data.guilds.get(caller.guild_id).await?;
+        let guild_lock = guild.lock().await;
+        (guild_lock.vote_skip, guild_lock.vote_skip_threshold)
+    };
+    let skips_directly = !vote_skip || caller.has_dj_role().await? || caller.is_alone();
+
+    let mut queue_lock = queue.lock().await;
+    let requester = match &queue_lock.current_track {
+        Some(track) => track.requester,
+        None => return Err("No track currently playing".into()),
+    };
+    if skips_directly || requester == caller.user_id {
+        queue_lock.skip(lava).await?;
+        return Ok("Track skipped".to_string());
+    }
+
+    if !queue_lock.add_skip_vote(caller.user_id) {
+        return Err("You have already voted to skip this track".into());
+    }
+    let listeners = caller.bot_listeners();
+    let votes = queue_lock.skip_votes(&listeners);
+    let required = required_votes(listeners.len(), threshold);
+    if votes >= required {
+        queue_lock.skip(lava).await?;
+        Ok(format!("Vote passed ({votes}/{required}), track skipped"))
     } else {
-        Err(Join(
-            "You must in a voice channel to use this command.".to_string(),
-        ))
+        Ok(format!("Voted to skip ({votes}/{required})"))
     }
 }
 
+pub fn progress_bar(position: u64, length: u64) -> String {
+    let bar1 = if length == 0 {
+        0
+    } else {
+        (((position as f32 / length as f32) * 19.) as usize).min(19)
+    };
+    let bar2 = 19 - bar1;
+    "▬".repeat(bar1) + "🔘" + &"▬".repeat(bar2)
+}
+
+pub fn required_votes(listeners: usize, threshold: u8) -> usize {
+    let required = (listeners * threshold as usize + 99) / 100;
+    required.max(1)
+}
+
+pub fn listeners(
+    ctx: &serenity::prelude::Context,
+    guild: &Guild,
+    channel_id: ChannelId,
+) -> Vec<UserId> {
+    guild
+        .voice_states
+        .values()
+        .filter(|voice_state| voice_state.channel_id == Some(channel_id))
+        .map(|voice_state| voice_state.user_id)
+        .filter(|user_id| !ctx.cache.user(*user_id).map_or(false, |user| user.bot))
+        .collect()
+}
+
 pub async fn join(
     ctx: &Context<'_>,
     guild_id: GuildId,
     channel_id: ChannelId,
     text_channel_id: ChannelId,
 ) -> Result<(LavalinkClient, Arc<Mutex<Queue>>), Error> {
-    let manager = songbird::get(ctx.serenity_context())
+    let data = ctx.data();
+    let lava_client = data.lavalink.clone();
+    connect(ctx.serenity_context(), &lava_client, guild_id, channel_id).await?;
+
+    let configured_channel_id = data
+        .guilds
+        .get(guild_id)
+        .await?
+        .lock()
+        .await
+        .text_channel_id;
+    let queue = data.guilds.get_queue(guild_id).await?;
+    {
+        let mut queue_lock = queue.lock().await;
+        queue_lock.channel_id = Some(configured_channel_id.unwrap_or(text_channel_id));
+        queue_lock.voice_channel_id = Some(channel_id);
+        if queue_lock.current_track.is_none() {
+            queue_lock.inactivity.idle_since = Some(Instant::now());
+        }
+        queue_lock.save().await;
+    }
+
+    Ok((lava_client, queue))
+}
+
+pub async fn connect(
+    ctx: &serenity::prelude::Context,
+    lava: &LavalinkClient,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<(), Error> {
+    let manager = songbird::get(ctx)
         .await
         .expect("Missing Songbird client")
         .clone();
@@ -73,19 +441,184 @@ pub async fn join(
         return Err(Join(why.to_string()));
     }
 
-    let data = ctx.data();
-    let queue = data.guilds.get_queue(guild_id).await;
+    if let Err(why) = lava.create_session_with_songbird(&info).await {
+        return Err(Join(why.to_string()));
+    }
+
+    Ok(())
+}
+
+pub async fn disconnect(
+    ctx: &serenity::prelude::Context,
+    lava: &LavalinkClient,
+    queue: &mut Queue,
+    guild_id: GuildId,
+) -> Result<(), Error> {
+    let manager = songbird::get(ctx)
+        .await
+        .expect("Missing Songbird client")
+        .clone();
+
+    queue.clean_up().await;
+    lava.destroy(guild_id).await?;
+    manager.remove(guild_id).await?;
+
+    Ok(())
+}
+
+pub async fn restore_queues(
+    ctx: &serenity::prelude::Context,
+    lava: &LavalinkClient,
+    guilds: &Guilds,
+    database: &PgPool,
+) {
+    let rows = match sqlx::query!(
+        "SELECT guild_id, voice_channel_id, position FROM queues WHERE voice_channel_id IS NOT NULL"
+    )
+    .fetch_all(database)
+    .await
     {
+        Ok(rows) => rows,
+        Err(why) => {
+            error!("Error fetching saved queues: {}", why);
+            return;
+        }
+    };
+
+    for row in rows {
+        let guild_id = GuildId(row.guild_id as u64);
+        let channel_id = match row.voice_channel_id {
+            Some(id) => ChannelId(id as u64),
+            None => continue,
+        };
+        if !guilds.contains(guild_id).await {
+            continue;
+        }
+
+        let queue = match guilds.get_queue(guild_id).await {
+            Ok(queue) => queue,
+            Err(why) => {
+                error!("Error getting queue in guild {}: {}", guild_id, why);
+                continue;
+            }
+        };
         let mut queue_lock = queue.lock().await;
-        queue_lock.channel_id = Some(text_channel_id);
+        if queue_lock.current_track.is_none() {
+            continue;
+        }
+        if let Err(why) = connect(ctx, lava, guild_id, channel_id).await {
+            error!(
+                "Error rejoining voice channel in guild {}: {}",
+                guild_id, why
+            );
+            continue;
+        }
+        let position = Duration::from_millis(row.position as u64);
+        if let Err(why) = queue_lock.resume(lava, position).await {
+            error!("Error resuming track in guild {}: {}", guild_id, why);
+            continue;
+        }
+        info!("Restored queue in guild {guild_id}");
     }
+}
 
-    let lava_client = data.lavalink.clone();
-    if let Err(why) = lava_client.create_session_with_songbird(&info).await {
-        return Err(Join(why.to_string()));
+pub fn parse_ranges(input: &str) -> Result<Vec<RangeInclusive<usize>>, Error> {
+    input
+        .split(',')
+        .map(|part| {
+            let part = part.trim();
+            let (start, end) = part.split_once('-').unwrap_or((part, part));
+            match (start.trim().parse::<usize>(), end.trim().parse::<usize>()) {
+                (Ok(start), Ok(end)) if start >= 1 && start <= end => Ok(start..=end),
+                _ => Err(Error::Command(format!("Invalid position: {part}"))),
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy)]
+pub enum Seek {
+    To(Duration),
+    Forward(Duration),
+    Back(Duration),
+}
+impl Seek {
+    pub fn position(self, current: Duration, length: Duration) -> Duration {
+        match self {
+            Seek::To(time) => time,
+            Seek::Forward(time) => current.saturating_add(time),
+            Seek::Back(time) => current.saturating_sub(time),
+        }
+        .min(length)
     }
+}
 
-    Ok((lava_client, queue))
+// accepts "90", "1:30", "1:02:30" and the relative "+30" and "-1:00"
+pub fn parse_seek(input: &str) -> Result<Seek, Error> {
+    let input = input.trim();
+    let seek = if let Some(time) = input.strip_prefix('+') {
+        parse_time(time).map(Seek::Forward)
+    } else if let Some(time) = input.strip_prefix('-') {
+        parse_time(time).map(Seek::Back)
+    } else {
+        parse_time(input).map(Seek::To)
+    };
+    seek.ok_or_else(|| Error::Command(format!("Invalid time: {input}")))
+}
+
+pub fn parse_time(input: &str) -> Option<Duration> {
+    let parts: Vec<&str> = input.trim().split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+    let mut seconds: u64 = 0;
+    for (i, part) in parts.iter().enumerate() {
+        let value: u64 = part.parse().ok()?;
+        // only the first part can go past 59
+        if i > 0 && value >= 60 {
+            return None;
+        }
+        seconds = seconds.checked_mul(60)?.checked_add(value)?;
+    }
+    Some(Duration::from_secs(seconds))
+}
+
+pub fn is_youtube(url: &str) -> bool {
+    url.contains("youtube.com/") || url.contains("youtu.be/")
+}
+
+// the t= parameter of youtube links, e.g. "t=90", "t=90s" or "t=1m30s"
+pub fn start_time(url: &str) -> Option<Duration> {
+    if !is_youtube(url) {
+        return None;
+    }
+    let (_, params) = url.split_once('?')?;
+    let value = params
+        .split(['&', '#'])
+        .find_map(|param| param.strip_prefix("t="))?;
+
+    let mut seconds: u64 = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm' | 's' => {
+                let unit = match c {
+                    'h' => 3600,
+                    'm' => 60,
+                    _ => 1,
+                };
+                let value = number.parse::<u64>().ok()?.checked_mul(unit)?;
+                seconds = seconds.checked_add(value)?;
+                number.clear();
+            }
+            _ => return None,
+        }
+    }
+    if !number.is_empty() {
+        seconds = seconds.checked_add(number.parse().ok()?)?;
+    }
+    Some(Duration::from_secs(seconds)).filter(|time| !time.is_zero())
 }
 
 pub fn length_to_string(dur: u64) -> String {
@@ -100,3 +633,70 @@ pub fn length_to_string(dur: u64) -> String {
     string += &format!("{:>02}:{:>02}", minutes, seconds);
     string
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse_ranges("3-7").ok(), Some(vec![3..=7]));
+        assert_eq!(parse_ranges("1,4,9").ok(), Some(vec![1..=1, 4..=4, 9..=9]));
+        assert_eq!(parse_ranges(" 2 - 4, 6 ").ok(), Some(vec![2..=4, 6..=6]));
+        assert_eq!(parse_ranges("5-5").ok(), Some(vec![5..=5]));
+    }
+
+    #[test]
+    fn invalid_ranges() {
+        for input in ["", "0", "0-3", "7-3", "a", "1-", "-2", "1,,2", "1-2-3"] {
+            assert!(parse_ranges(input).is_err(), "{input}");
+        }
+        assert!(parse_ranges("99999999999999999999999").is_err());
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(parse_time("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_time("1:30"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_time("1:02:30"), Some(Duration::from_secs(3750)));
+        assert_eq!(parse_time(" 2:00 "), Some(Duration::from_secs(120)));
+        for input in ["", "a", "1:60", "1:2:3:4", "-5", "1.5", "1::2"] {
+            assert_eq!(parse_time(input), None, "{input}");
+        }
+        assert_eq!(parse_time("18446744073709551615:00"), None);
+        assert_eq!(parse_time("18446744073709551616"), None);
+    }
+
+    #[test]
+    fn seeks() {
+        let current = Duration::from_secs(60);
+        let length = Duration::from_secs(300);
+        let position = |input| parse_seek(input).unwrap().position(current, length);
+        assert_eq!(position("+30"), Duration::from_secs(90));
+        assert_eq!(position("-1:00"), Duration::ZERO);
+        assert_eq!(position("-2:00"), Duration::ZERO);
+        assert_eq!(position("2:00"), Duration::from_secs(120));
+        assert_eq!(position("10:00"), length);
+        assert_eq!(position("+18446744073709551615"), length);
+        assert!(parse_seek("+").is_err());
+        assert!(parse_seek("abc").is_err());
+    }
+
+    #[test]
+    fn start_times() {
+        let start = |url| start_time(url).map(|time| time.as_secs());
+        assert_eq!(start("https://www.youtube.com/watch?v=a&t=1m30s"), Some(90));
+        assert_eq!(start("https://youtu.be/a?t=90"), Some(90));
+        assert_eq!(start("https://youtu.be/a?t=90s"), Some(90));
+        assert_eq!(
+            start("https://www.youtube.com/watch?t=1h2m3s&v=a"),
+            Some(3723)
+        );
+        assert_eq!(start("https://www.youtube.com/watch?v=a"), None);
+        assert_eq!(start("https://www.youtube.com/watch?v=a&t=0"), None);
+        assert_eq!(start("https://www.youtube.com/watch?v=a&t=1x"), None);
+        assert_eq!(start("https://example.com/watch?v=a&t=30"), None);
+        assert_eq!(start("https://youtu.be/a?t=99999999999999999h"), None);
+        assert_eq!(start("https://youtu.be/a?t=18446744073709551615s1"), None);
+    }
+}
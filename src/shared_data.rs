@@ -1,5 +1,9 @@
 use crate::{
-    config::Config, error::Error, events::LavalinkHandler, guild::Guild, music::queue::Queue,
+    config::Config,
+    error::Error,
+    events::LavalinkHandler,
+    guild::Guild,
+    music::{queue::Queue, sponsorblock::SponsorBlock},
 };
 use genius_rs::Genius as GeniusClient;
 use lavalink_rs::LavalinkClient;
@@ -9,18 +13,38 @@ use sqlx::{postgres::PgPoolOptions, PgPool};
 use std::{collections::HashMap, sync::Arc};
 use tracing::info;
 
+#[derive(Clone)]
 pub struct Guilds {
     pub inner: Arc<Mutex<HashMap<GuildId, Arc<Mutex<Guild>>>>>,
+    pub database: PgPool,
 }
 impl Guilds {
-    pub async fn get(&self, guild_id: GuildId) -> Arc<Mutex<Guild>> {
-        let inner_lock = self.inner.lock().await;
-        inner_lock.get(&guild_id).unwrap().clone()
+    pub async fn get(&self, guild_id: GuildId) -> Result<Arc<Mutex<Guild>>, Error> {
+        if let Some(guild) = self.inner.lock().await.get(&guild_id) {
+            return Ok(guild.clone());
+        }
+
+        // loading hits the database, so other guilds aren't kept waiting on it.
+        // if the guild was loaded in the meantime, that one is kept
+        let guild = Guild::new(guild_id, &self.database).await?;
+        Ok(self
+            .inner
+            .lock()
+            .await
+            .entry(guild_id)
+            .or_insert(guild)
+            .clone())
     }
-    pub async fn get_queue(&self, guild_id: GuildId) -> Arc<Mutex<Queue>> {
-        let guild = self.get(guild_id).await;
+    pub async fn get_queue(&self, guild_id: GuildId) -> Result<Arc<Mutex<Queue>>, Error> {
+        let guild = self.get(guild_id).await?;
         let guild_lock = guild.lock().await;
-        guild_lock.queue.clone()
+        Ok(guild_lock.queue.clone())
+    }
+    pub async fn contains(&self, guild_id: GuildId) -> bool {
+        self.inner.lock().await.contains_key(&guild_id)
+    }
+    pub async fn remove(&self, guild_id: GuildId) -> Option<Arc<Mutex<Guild>>> {
+        self.inner.lock().await.remove(&guild_id)
     }
 }
 
@@ -49,26 +73,13 @@ impl Data {
         let mut guilds = HashMap::default();
 
         for guild in &ready.guilds {
-            guilds.insert(
-                guild.id,
-                crate::guild::Guild::new(guild.id, &database).await,
-            );
+            guilds.insert(guild.id, Guild::new(guild.id, &database).await?);
         }
 
-        let guilds = Arc::new(Mutex::new(guilds));
-
-        let lavalink = LavalinkClient::builder(ready.user.id.0)
-            .set_host(&config.lava_address)
-            .set_port(config.lava_port)
-            .set_password(&config.lava_password)
-            .build(LavalinkHandler {
-                guilds: Guilds {
-                    inner: guilds.clone(),
-                },
-                http: ctx.http.clone(),
-            })
-            .await?;
-        info!("Connected to lavalink");
+        let guilds = Guilds {
+            inner: Arc::new(Mutex::new(guilds)),
+            database: database.clone(),
+        };
 
         let spotify_creds = Credentials {
             id: config.spotify_id,
@@ -82,6 +93,34 @@ impl Data {
         let spotify = ClientCredsSpotify::with_config(spotify_creds, spotify_config);
         spotify.request_token().await.unwrap();
 
+        let lavalink = LavalinkClient::builder(ready.user.id.0)
+            .set_host(&config.lava_address)
+            .set_port(config.lava_port)
+            .set_password(&config.lava_password)
+            .build(LavalinkHandler {
+                guilds: guilds.clone(),
+                http: ctx.http.clone(),
+                spotify: spotify.clone(),
+                sponsorblock: config.sponsorblock_url.as_deref().map(SponsorBlock::new),
+            })
+            .await?;
+        info!("Connected to lavalink");
+
+        {
+            let ctx = ctx.clone();
+            let lavalink = lavalink.clone();
+            let guilds = guilds.clone();
+            let db = database.clone();
+            tokio::spawn(async move {
+                crate::music::utils::restore_queues(&ctx, &lavalink, &guilds, &db).await;
+            });
+        }
+        tokio::spawn(crate::music::inactivity::run(
+            ctx.clone(),
+            lavalink.clone(),
+            guilds.clone(),
+        ));
+
         let genius = GeniusClient::new(config.genius_token);
         let shard_manager = framework.shard_manager().clone();
         let ctx = Arc::new(ctx.clone());
@@ -97,7 +136,7 @@ impl Data {
 
         Ok(Self {
             database,
-            guilds: Guilds { inner: guilds },
+            guilds,
             lavalink,
             spotify,
             genius,